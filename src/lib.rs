@@ -17,28 +17,122 @@
 //!
 //! # How to use
 //! 1. place all your async code in a mod. By default, the mod should call `amphi`,
-//! but it can be customize.
+//!    but it can be customize.
 //! 2. apply `amphi` attribute macro on the mod declaration code.
 //!
+//! # Tags
+//! Code inside the mod can be kept in one version only with `#[amphi(..)]` tags on
+//! items of any kind, including `use` and items declared in function bodies, impl
+//! and trait items, fields, variants, match arms, function and generic parameters,
+//! `let` statements and expressions, like `#[amphi(blocking)]` or
+//! `#[amphi(asynchronous)]`. Tags combine with `any(..)`, `all(..)` and `not(..)`,
+//! like `#[amphi(not(blocking))]`. Code is kept when all its tags hold, and the tags
+//! are removed. Other code is removed from its block or list, like the arguments of
+//! a call or the elements of an array, a tuple or a struct literal, and tagging an
+//! expression that cannot be removed, like an operand, is an error. So is an
+//! unknown tag.
+//!
+//! A tag followed by `attr(..)`, like `#[amphi(blocking, attr(derive(Clone)))]`,
+//! keeps the code in both versions and adds the given attributes to it in the
+//! versions where the tag holds.
+//!
+//! Tags can also be written `#[amphi::amphi(..)]`, or under a condition like
+//! `#[cfg_attr(feature = "x", amphi(blocking))]`. Code whose conditional tag does
+//! not hold is kept as `#[cfg(not(feature = "x"))]`, so it still depends on the
+//! condition, and attributes given with `attr(..)` are added under the condition.
+//!
+//! # Streams
+//! In the blocking version, streams become iterators: `stream::iter`, `unfold`
+//! and friends become their `core::iter` counterparts, `StreamExt` and
+//! `TryStreamExt` combinators become `Iterator` ones, and boxed streams become
+//! boxed iterators. The body of `stream! { .. }` and `try_stream! { .. }` runs to
+//! completion up front, collecting the yielded items, before the first item is
+//! read. Endless streams should use `stream::unfold` or `stream::repeat_with`,
+//! which stay lazy, and a `loop` that cannot end in a stream body is an error.
+//!
+//! # Options
+//! Options are given as arguments of the `amphi` attribute, like
+//! `#[amphi(blocking_only, macros(my_macro))]`.
+//! - `async_only`, `blocking_only`: generate only one of the two versions.
+//! - `path = "src/lib.rs"`: file of the amphi mod, to look up mods declared with
+//!   `declare_mod!`.
+//! - `join = "thread"`: run the futures given to `join!`, `try_join!` and
+//!   `join_all(iter.map(f))` on scoped threads in the blocking version, instead of
+//!   one after another. Defaults to `join = "sequential"`.
+//! - `macros(my_macro, ..)`: convert arguments of the given macros like the ones of
+//!   std macros such as `assert_eq!`, `format!` or `vec!`.
+//! - `macro_rules`: strip `.await` and `async` from the bodies of `macro_rules!`
+//!   declared in the amphi mod.
+//! - `replace(reqwest::Client => reqwest::blocking::Client, ..)`: substitute paths
+//!   starting with the left hand side in the blocking version, in imports, types,
+//!   expressions and trait bounds. An import whose name changes keeps the old name
+//!   as an alias. An entry matching nothing is an error.
+//! - `preset = "tokio"`: convert a well known async crate to its blocking counterpart.
+//!   Can be given more than once. The available presets are:
+//!   - `tokio`: `tokio::fs`, `tokio::io` and `tokio::net` to `std::fs`, `std::io`
+//!     and `std::net`, with `AsyncRead`, `AsyncWrite`, `AsyncBufRead` and their
+//!     extension traits to `Read`, `Write` and `BufRead`. `tokio::spawn(async move { .. })`
+//!     to `std::thread::spawn(move || { .. })`, and `handle.await` to `handle.join()`,
+//!     so that `handle.await.unwrap()` becomes `handle.join().unwrap()`. Handles are
+//!     joined when the task is awaited right away or bound by a `let` that is only
+//!     awaited; keeping them otherwise, like in a `Vec`, is an error.
+//!     `spawn_blocking(f).await` to an `Ok` of the direct call. `tokio::time::sleep`
+//!     to `std::thread::sleep`, and `tokio::sync::{Mutex, RwLock, mpsc, oneshot}`
+//!     to `std::sync`. On the locks and receivers bound by a `let`, like
+//!     `Arc::new(Mutex::new(0))` or `mpsc::channel(4)`, or a parameter of their
+//!     type, `.lock().await` becomes `.lock().unwrap()` and `.recv().await` becomes
+//!     `.recv().ok()`. APIs without a std counterpart, like `tokio::io::split`,
+//!     `timeout`, `interval`, `broadcast` or `read_u32` of a value of a tokio I/O
+//!     type or trait, are errors.
+//!   - `async-std`: `async_std::{fs, io, net, os, path, sync}` to the `std` modules
+//!     they mirror, `async_std::prelude` to `std::io::prelude`, and the `futures-io`
+//!     traits `AsyncRead`, `AsyncWrite`, `AsyncBufRead`, `AsyncSeek` and their
+//!     extension traits to `std::io` ones. `async_std::task` to `std::thread`, with
+//!     `task::spawn(async move { .. })` to a thread joined when awaited, with the
+//!     same restriction on handles as `tokio`, `block_on(f)` to `f` and
+//!     `spawn_blocking(f).await` to `f()`. `.lock().await` on bound locks becomes
+//!     `.lock().unwrap()` as with `tokio`.
+//!   - `reqwest`: `reqwest::{Client, ClientBuilder, RequestBuilder, Response}` to
+//!     `reqwest::blocking` ones, so that `.send().await?.json().await?` chains work
+//!     as is. Streaming bodies, like `bytes_stream()` or `Body::wrap_stream`, are
+//!     errors.
+//!   - `embedded-hal`: `embedded_hal_async` traits, like `i2c::I2c`, `spi::SpiDevice`
+//!     and `delay::DelayNs`, to the `embedded_hal` ones, and `digital::Wait` to
+//!     `digital::InputPin` with `wait_for_high()` and friends polling the pin. The
+//!     expansion stays `no_std`, so conversions needing threads or allocation from
+//!     `std` are errors: `join!` with `join = "thread"`, `join_all`, `stream!`,
+//!     `BoxStream` and `.boxed()` streams. `Box::pin(stream)` becomes
+//!     `Box::new(stream)` with the same `Box`.
+//!   - `tungstenite`: `tokio_tungstenite` to `tungstenite`, like `connect_async` and
+//!     `accept_async` to `connect` and `accept`. `ws.next().await` on a WebSocket
+//!     bound by `let` or a parameter reads the next message, `None` once closed,
+//!     and `SinkExt::send` writes it.
+//!   - `postgres`: `tokio_postgres` to `postgres`. `let (client, connection) =
+//!     connect(..)` becomes `let mut client = connect(..)` and the task spawned to
+//!     drive the connection is removed, since the blocking client owns it. Clients
+//!     and queried transactions are bound `mut`, their blocking methods take
+//!     `&mut self`.
+//!
 //! # LICENSE
 //! MIT
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
+use std::collections::HashSet;
 use std::ffi::OsStr;
 use std::path::PathBuf;
 
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::{parse_macro_input, spanned::Spanned, AttributeArgs, Ident, Item, Lit, Meta, NestedMeta};
 
-use crate::parse::ItemModRestrict;
-use crate::visit::{AmphiConversion, AsyncAwaitRemoval};
+use crate::parse::{AmphiArg, AmphiArgsRestrict, ItemModRestrict};
+use crate::visit::{preset, AmphiConversion, AsyncAwaitRemoval, Join, Options};
 
 mod parse;
 mod visit;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 enum Version {
     Sync,
     Async,
@@ -63,24 +157,43 @@ enum Mode {
 struct AmphiArgs {
     mode: Mode,
     path: PathBuf,
+    options: Options,
 }
 
-fn parse_args(attr_args: AttributeArgs) -> Result<AmphiArgs, (Span, &'static str)> {
-    if attr_args.len() > 2 {
-        return Err((Span::call_site(), "Only up to two argument is accepted"));
-    }
+const UNKNOWN_OPTION: &str =
+    "Only allow `async_only`, `blocking_only`, `path`, `join`, `macros`, `macro_rules`, `replace` or `preset`";
+
+fn parse_args(attr_args: Vec<AmphiArg>) -> Result<AmphiArgs, (Span, &'static str)> {
     let mut args = AmphiArgs {
         mode: Mode::Both,
         path: PathBuf::from("src"),
+        options: Options::default(),
     };
-    for attr in &attr_args {
+    let mut given = HashSet::new();
+    for arg in &attr_args {
+        let attr = match arg {
+            AmphiArg::Replace(ident, entries) => {
+                if !given.insert(ident.to_string()) {
+                    return Err((ident.span(), "Each option can only be given once"));
+                }
+                args.options.replace.extend(entries.iter().cloned());
+                continue;
+            }
+            AmphiArg::Meta(attr) => attr,
+        };
         match attr {
             NestedMeta::Lit(lit) => {
                 return Err((lit.span(), "Arguments should not be literal"));
             }
-            NestedMeta::Meta(meta) => match &meta {
-                Meta::NameValue(meta_name_value) => {
-                    if meta_name_value.path.is_ident("path") {
+            NestedMeta::Meta(meta) => {
+                // presets can be combined
+                if !meta.path().is_ident("preset")
+                    && !given.insert(meta.path().to_token_stream().to_string())
+                {
+                    return Err((meta.span(), "Each option can only be given once"));
+                }
+                match &meta {
+                    Meta::NameValue(meta_name_value) if meta_name_value.path.is_ident("path") => {
                         let path_value = if let Lit::Str(lit_str) = &meta_name_value.lit {
                             lit_str.value()
                         } else {
@@ -98,44 +211,79 @@ fn parse_args(attr_args: AttributeArgs) -> Result<AmphiArgs, (Span, &'static str
                         } else {
                             return Err((meta_name_value.lit.span(), "file not found"));
                         }
-                    } else {
-                        return Err((
-                            meta.span(),
-                            "Only allow `async_only`, `blocking_only`, or `path`",
-                        ));
                     }
-                }
-                Meta::Path(path) => {
-                    if path.is_ident("async_only") {
-                        if args.mode == Mode::SyncOnly {
-                            return Err((
-                                meta.span(),
-                                "Option `async_only`, `blocking_only` are mutually exclusive",
-                            ));
+                    Meta::NameValue(meta_name_value) if meta_name_value.path.is_ident("join") => {
+                        args.options.join = match &meta_name_value.lit {
+                            Lit::Str(lit_str) if lit_str.value() == "sequential" => {
+                                Join::Sequential
+                            }
+                            Lit::Str(lit_str) if lit_str.value() == "thread" => Join::Thread,
+                            _ => {
+                                return Err((
+                                    meta_name_value.lit.span(),
+                                    "join should be either \"sequential\" or \"thread\"",
+                                ));
+                            }
+                        };
+                    }
+                    Meta::NameValue(meta_name_value) if meta_name_value.path.is_ident("preset") => {
+                        let preset = match &meta_name_value.lit {
+                            Lit::Str(lit_str) => preset::find(&lit_str.value()),
+                            _ => None,
+                        };
+                        match preset {
+                            Some(preset) => args.options.presets.push(preset),
+                            None => {
+                                return Err((meta_name_value.lit.span(), preset::UNKNOWN_PRESET))
+                            }
                         }
-                        args.mode = Mode::AsyncOnly;
-                    } else if path.is_ident("blocking_only") {
-                        if args.mode == Mode::AsyncOnly {
-                            return Err((
-                                meta.span(),
-                                "Option `async_only`, `blocking_only` is mutually exclusive",
-                            ));
+                    }
+                    Meta::List(list) if list.path.is_ident("macros") => {
+                        for nested in &list.nested {
+                            match nested {
+                                NestedMeta::Meta(Meta::Path(path))
+                                    if path.get_ident().is_some() =>
+                                {
+                                    let ident = path.get_ident().unwrap();
+                                    args.options.macros.push(ident.to_string());
+                                }
+                                _ => {
+                                    return Err((
+                                        nested.span(),
+                                        "`macros` only accepts macro names, like `macros(my_macro)`",
+                                    ));
+                                }
+                            }
                         }
-                        args.mode = Mode::SyncOnly;
-                    } else {
-                        return Err((
-                            meta.span(),
-                            "Only allow `async_only`, `blocking_only`, or `path`",
-                        ));
+                    }
+                    Meta::Path(path) => {
+                        if path.is_ident("async_only") {
+                            if args.mode == Mode::SyncOnly {
+                                return Err((
+                                    meta.span(),
+                                    "Option `async_only`, `blocking_only` are mutually exclusive",
+                                ));
+                            }
+                            args.mode = Mode::AsyncOnly;
+                        } else if path.is_ident("blocking_only") {
+                            if args.mode == Mode::AsyncOnly {
+                                return Err((
+                                    meta.span(),
+                                    "Option `async_only`, `blocking_only` is mutually exclusive",
+                                ));
+                            }
+                            args.mode = Mode::SyncOnly;
+                        } else if path.is_ident("macro_rules") {
+                            args.options.macro_rules = true;
+                        } else {
+                            return Err((meta.span(), UNKNOWN_OPTION));
+                        }
+                    }
+                    _ => {
+                        return Err((meta.span(), UNKNOWN_OPTION));
                     }
                 }
-                _ => {
-                    return Err((
-                        meta.span(),
-                        "Only allow `async_only`, `blocking_only`, or `path`",
-                    ));
-                }
-            },
+            }
         }
     }
 
@@ -149,7 +297,7 @@ fn parse_args(attr_args: AttributeArgs) -> Result<AmphiArgs, (Span, &'static str
 
 #[proc_macro_attribute]
 pub fn amphi(args: TokenStream, input: TokenStream) -> TokenStream {
-    let attr_args = parse_macro_input!(args as AttributeArgs);
+    let attr_args = parse_macro_input!(args as AmphiArgsRestrict).0;
     let amphi_args = match parse_args(attr_args) {
         Ok(mode) => mode,
         Err((span, message)) => {
@@ -172,12 +320,21 @@ pub fn amphi(args: TokenStream, input: TokenStream) -> TokenStream {
         Mode::Both => (quote!(#sync), quote!(#asynchronous)),
     };
 
-    let asynchronous_mod =
-        AmphiConversion::new(Version::Async, mod_name.as_str(), amphi_args.path.clone())
-            .convert(asynchronous);
-    let sync_mod =
-        AmphiConversion::new(Version::Sync, mod_name.as_str(), amphi_args.path).convert(sync);
-    let sync_mod = AsyncAwaitRemoval.remove_async_await(sync_mod);
+    let asynchronous_mod = AmphiConversion::new(
+        Version::Async,
+        mod_name.as_str(),
+        amphi_args.path.clone(),
+        amphi_args.options.clone(),
+    )
+    .convert(asynchronous);
+    let sync_mod = AmphiConversion::new(
+        Version::Sync,
+        mod_name.as_str(),
+        amphi_args.path,
+        amphi_args.options.clone(),
+    )
+    .convert(sync);
+    let sync_mod = AsyncAwaitRemoval::new(amphi_args.options).remove_async_await(sync_mod);
 
     (quote! {
         #asynchronous_mod
@@ -190,7 +347,7 @@ fn parse_test_args(attr_args: AttributeArgs) -> Result<String, (Span, &'static s
     match attr_args.len() {
         0 => Ok("amphi".to_string()),
         1 => {
-            let attr = attr_args.get(0).unwrap();
+            let attr = attr_args.first().unwrap();
             match attr {
                 NestedMeta::Lit(lit) => {
                     if let Lit::Str(mod_name) = lit {
@@ -244,8 +401,9 @@ pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let input = TokenStream2::from(input);
 
-    let sync = AmphiConversion::new(Version::Sync, mod_name.as_str(), None).convert(input.clone());
-    let sync = AsyncAwaitRemoval.remove_async_await(sync);
+    let sync = AmphiConversion::new(Version::Sync, mod_name.as_str(), None, Options::default())
+        .convert(input.clone());
+    let sync = AsyncAwaitRemoval::new(Options::default()).remove_async_await(sync);
     let sync_ts = sync.clone().into();
     let sync_test = match &mut parse_macro_input!(sync_ts as Item) {
         Item::Fn(item_fn) => {
@@ -258,7 +416,8 @@ pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let asynchronous_test =
-        AmphiConversion::new(Version::Async, mod_name.as_str(), None).convert(input.clone());
+        AmphiConversion::new(Version::Async, mod_name.as_str(), None, Options::default())
+            .convert(input.clone());
 
     let test_code = quote! {
         #[test]
@@ -1,6 +1,9 @@
+use proc_macro2::Ident;
 use syn::{
+    parenthesized,
     parse::{Parse, ParseStream, Result},
-    Attribute, ItemMod, Token,
+    punctuated::Punctuated,
+    token, Attribute, ItemMod, NestedMeta, Path, Token,
 };
 
 pub struct ItemModRestrict(pub ItemMod);
@@ -32,3 +35,55 @@ impl Parse for ItemModRestrict {
         }
     }
 }
+
+/// A `from => to` entry of `replace(..)`
+#[derive(Clone)]
+pub struct Replacement {
+    pub from: Path,
+    pub to: Path,
+}
+
+impl Parse for Replacement {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let from = input.call(Path::parse_mod_style)?;
+        input.parse::<Token![=>]>()?;
+        let to = input.call(Path::parse_mod_style)?;
+        Ok(Replacement { from, to })
+    }
+}
+
+/// An argument of `#[amphi(..)]`
+pub enum AmphiArg {
+    /// `replace(from => to, ..)`, whose entries are not meta items
+    Replace(Ident, Vec<Replacement>),
+    Meta(NestedMeta),
+}
+
+/// Arguments of `#[amphi(..)]`, like `AttributeArgs` with `replace(..)` in
+/// addition.
+pub struct AmphiArgsRestrict(pub Vec<AmphiArg>);
+
+impl Parse for AmphiArgsRestrict {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut args = vec![];
+        while !input.is_empty() {
+            let ahead = input.fork();
+            let is_replace = ahead.parse::<Ident>().is_ok_and(|ident| ident == "replace")
+                && ahead.peek(token::Paren);
+            if is_replace {
+                let ident: Ident = input.parse()?;
+                let content;
+                parenthesized!(content in input);
+                let entries = Punctuated::<Replacement, Token![,]>::parse_terminated(&content)?;
+                args.push(AmphiArg::Replace(ident, entries.into_iter().collect()));
+            } else {
+                args.push(AmphiArg::Meta(input.parse()?));
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(AmphiArgsRestrict(args))
+    }
+}
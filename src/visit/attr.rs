@@ -1,26 +1,234 @@
-use crate::visit::attr;
-use proc_macro2::TokenTree;
-use syn::{Attribute, Item, ItemMod};
-
-pub fn find_attribute(attrs: &Vec<Attribute>, to_match: &str) -> bool {
-    attrs
-        .iter()
-        .take_while(|attr| {
-            if let 1 = attr.path.segments.len() {
-                if attr.path.is_ident("amphi") {
-                    let tree: TokenTree = syn::parse(attr.tokens.clone().into()).unwrap();
-                    if let TokenTree::Group(group) = tree {
-                        let arg = group.stream().to_string();
-                        if arg.as_str() == to_match {
-                            return false;
-                        }
-                    }
+use proc_macro2::{Delimiter, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream, Parser},
+    parse_quote,
+    punctuated::Punctuated,
+    token, Arm, Attribute, Expr, Field, FieldValue, FnArg, GenericParam, Ident, ImplItem, Item,
+    Path, Stmt, Token, TraitItem, Variant,
+};
+
+use crate::Version;
+
+const UNKNOWN_TAG: &str =
+    "Unknown tag, only `asynchronous`, `blocking`, `any(..)`, `all(..)` and `not(..)` are allowed";
+
+/// the condition of a `#[amphi(..)]` tag, like `blocking` or
+/// `not(any(asynchronous, ..))`
+pub enum Predicate {
+    Version(Version),
+    Any(Vec<Predicate>),
+    All(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Parse for Predicate {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: Ident = input.parse()?;
+        if !input.peek(token::Paren) {
+            return match ident.to_string().as_str() {
+                "asynchronous" => Ok(Predicate::Version(Version::Async)),
+                "blocking" => Ok(Predicate::Version(Version::Sync)),
+                _ => Err(syn::Error::new(ident.span(), UNKNOWN_TAG)),
+            };
+        }
+        let content;
+        parenthesized!(content in input);
+        let mut nested: Vec<_> = Punctuated::<Predicate, Token![,]>::parse_terminated(&content)?
+            .into_iter()
+            .collect();
+        match ident.to_string().as_str() {
+            "any" => Ok(Predicate::Any(nested)),
+            "all" => Ok(Predicate::All(nested)),
+            "not" if nested.len() == 1 => Ok(Predicate::Not(Box::new(nested.remove(0)))),
+            "not" => Err(syn::Error::new(ident.span(), "`not` takes exactly one tag")),
+            _ => Err(syn::Error::new(ident.span(), UNKNOWN_TAG)),
+        }
+    }
+}
+
+impl Predicate {
+    /// whether the tagged code belongs to `version`
+    pub fn holds(&self, version: Version) -> bool {
+        match self {
+            Predicate::Version(tagged) => *tagged == version,
+            Predicate::Any(predicates) => predicates.iter().any(|p| p.holds(version)),
+            Predicate::All(predicates) => predicates.iter().all(|p| p.holds(version)),
+            Predicate::Not(predicate) => !predicate.holds(version),
+        }
+    }
+}
+
+/// whether an attribute is a tag, written `amphi(..)` or `amphi::amphi(..)`
+fn is_tag(path: &Path) -> bool {
+    path.is_ident("amphi")
+        || (path.segments.len() == 2
+            && path
+                .segments
+                .iter()
+                .all(|segment| segment.ident == "amphi" && segment.arguments.is_empty()))
+}
+
+/// A tag: the predicate, and the attributes given as `attr(..)`, which are
+/// added to the tagged code when the predicate holds instead of removing the
+/// code when it does not.
+struct Tag {
+    predicate: Predicate,
+    attrs: Option<Vec<TokenStream2>>,
+}
+
+impl Parse for Tag {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let predicate = input.parse()?;
+        if input.is_empty() {
+            return Ok(Tag {
+                predicate,
+                attrs: None,
+            });
+        }
+        input.parse::<Token![,]>()?;
+        let ident: Ident = input.parse()?;
+        if ident != "attr" {
+            return Err(syn::Error::new(
+                ident.span(),
+                "Only `attr(..)` can follow the tag, like `amphi(blocking, attr(derive(Clone)))`",
+            ));
+        }
+        let content;
+        parenthesized!(content in input);
+        let attrs = split_commas(content.parse()?);
+        for attr in &attrs {
+            parse_attr_path.parse2(attr.clone())?;
+        }
+        Ok(Tag {
+            predicate,
+            attrs: Some(attrs),
+        })
+    }
+}
+
+/// Parse the path and the arguments of an attribute, like `derive(Clone)`.
+fn parse_attr_path(input: ParseStream) -> syn::Result<(Path, TokenStream2)> {
+    Ok((input.call(Path::parse_mod_style)?, input.parse()?))
+}
+
+/// Parse the parenthesized content of a tag, like `(not(blocking))`.
+fn parse_tag(tokens: TokenStream2) -> syn::Result<Tag> {
+    match syn::parse2(tokens)? {
+        TokenTree::Group(group) if group.delimiter() == Delimiter::Parenthesis => {
+            syn::parse2(group.stream())
+        }
+        tree => Err(syn::Error::new(
+            tree.span(),
+            "Tags are given in parentheses, like `amphi(blocking)`",
+        )),
+    }
+}
+
+/// Split a token stream at its top level commas.
+fn split_commas(tokens: TokenStream2) -> Vec<TokenStream2> {
+    let mut chunks = vec![TokenStream2::new()];
+    for tree in tokens {
+        match &tree {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => chunks.push(TokenStream2::new()),
+            _ => chunks.last_mut().unwrap().extend(Some(tree)),
+        }
+    }
+    chunks.retain(|chunk| !chunk.is_empty());
+    chunks
+}
+
+/// Take the tags out of `#[cfg_attr(condition, amphi(..), ..)]`. Return the
+/// condition and the tags, and whether other attributes are left in it.
+fn take_cfg_attr_tags(attr: &mut Attribute) -> syn::Result<(TokenStream2, Vec<Tag>, bool)> {
+    let mut chunks = split_commas(attr.parse_args()?).into_iter();
+    let condition = chunks.next().unwrap_or_default();
+    let mut tags = vec![];
+    let mut rest = vec![];
+    for chunk in chunks {
+        // attributes that are not a path, like `unsafe(no_mangle)`, are kept as is
+        match parse_attr_path.parse2(chunk.clone()) {
+            Ok((path, args)) if is_tag(&path) => tags.push(parse_tag(args)?),
+            _ => rest.push(chunk),
+        }
+    }
+    if !tags.is_empty() {
+        attr.tokens = quote!((#condition, #(#rest),*));
+    }
+    Ok((condition, tags, !rest.is_empty()))
+}
+
+/// Evaluate the `#[amphi(..)]` tags for `version` and remove them. Return
+/// whether the tagged code is kept, which is when all of its tags hold.
+///
+/// The attributes of `#[amphi(blocking, attr(..))]` replace the tag if it
+/// holds, and the code is kept either way.
+///
+/// A tag in `#[cfg_attr(condition, amphi(..))]` only applies under the
+/// condition, so code whose tag does not hold is kept as
+/// `#[cfg(not(condition))]`, and its attributes are added as
+/// `#[cfg_attr(condition, ..)]`.
+pub fn tailor_attrs(attrs: &mut Vec<Attribute>, version: Version) -> syn::Result<bool> {
+    let mut keep = true;
+    let mut tailored: Vec<Attribute> = Vec::with_capacity(attrs.len());
+    for mut attr in std::mem::take(attrs) {
+        if is_tag(&attr.path) {
+            let tag = parse_tag(attr.tokens.clone())?;
+            let holds = tag.predicate.holds(version);
+            match tag.attrs {
+                Some(added) if holds => tailored.extend(added.iter().map(|a| parse_quote!(#[#a]))),
+                Some(_) => {}
+                None => keep &= holds,
+            }
+            continue;
+        }
+        if attr.path.is_ident("cfg_attr") {
+            let (condition, tags, left) = take_cfg_attr_tags(&mut attr)?;
+            for tag in &tags {
+                let holds = tag.predicate.holds(version);
+                match &tag.attrs {
+                    Some(added) if holds => tailored.extend(
+                        added
+                            .iter()
+                            .map(|a| -> Attribute { parse_quote!(#[cfg_attr(#condition, #a)]) }),
+                    ),
+                    None if !holds => tailored.push(parse_quote!(#[cfg(not(#condition))])),
+                    _ => {}
                 }
             }
-            true
-        })
-        .count()
-        < attrs.len()
+            if !tags.is_empty() && !left {
+                continue;
+            }
+        }
+        tailored.push(attr);
+    }
+    *attrs = tailored;
+    Ok(keep)
+}
+
+/// Remove attribute macros imported from `krate`, written either as `#[name]`
+/// or `#[krate::name]`, regardless of their arguments. Return the removed
+/// attributes.
+pub fn remove_attribute_macro(
+    attrs: &mut Vec<Attribute>,
+    krate: &str,
+    name: &str,
+) -> Vec<Attribute> {
+    let mut removed = vec![];
+    attrs.retain(|attr| {
+        let segments: Vec<_> = attr.path.segments.iter().collect();
+        let found = match segments.as_slice() {
+            [ident] => ident.ident == name,
+            [path, ident] => path.ident == krate && ident.ident == name,
+            _ => false,
+        };
+        if found {
+            removed.push(attr.clone());
+        }
+        !found
+    });
+    removed
 }
 
 #[allow(dead_code)]
@@ -41,103 +249,215 @@ pub fn pop_attribute(attrs: &mut Vec<Attribute>, ident: &str) -> Option<String>
     result
 }
 
-pub fn remove_matched_attribute(
-    attrs: &mut Vec<Attribute>,
-    ident: &str,
-    to_match: &str,
-) -> Option<String> {
-    let mut result = None;
-    attrs.retain(|attr| {
-        if let 1 = attr.path.segments.len() {
-            if attr.path.is_ident(ident) {
-                let tree: TokenTree = syn::parse(attr.tokens.clone().into()).unwrap();
-                if let TokenTree::Group(group) = tree {
-                    let arg = group.stream().to_string();
-                    if arg.as_str() == to_match {
-                        result = Some(arg);
-                        return false;
-                    }
-                }
-            }
+/// the attributes of an item, if it has any
+fn item_attrs(item: &mut Item) -> Option<&mut Vec<Attribute>> {
+    match item {
+        Item::Const(item) => Some(&mut item.attrs),
+        Item::Enum(item) => Some(&mut item.attrs),
+        Item::ExternCrate(item) => Some(&mut item.attrs),
+        Item::Fn(item) => Some(&mut item.attrs),
+        Item::ForeignMod(item) => Some(&mut item.attrs),
+        Item::Impl(item) => Some(&mut item.attrs),
+        Item::Macro(item) => Some(&mut item.attrs),
+        Item::Macro2(item) => Some(&mut item.attrs),
+        Item::Mod(item) => Some(&mut item.attrs),
+        Item::Static(item) => Some(&mut item.attrs),
+        Item::Struct(item) => Some(&mut item.attrs),
+        Item::Trait(item) => Some(&mut item.attrs),
+        Item::TraitAlias(item) => Some(&mut item.attrs),
+        Item::Type(item) => Some(&mut item.attrs),
+        Item::Union(item) => Some(&mut item.attrs),
+        Item::Use(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+/// Whether an item is kept in `version`. An item with a malformed tag is
+/// replaced by the error.
+fn tailor_item(item: &mut Item, version: Version) -> bool {
+    let attrs = match item_attrs(item) {
+        Some(attrs) => attrs,
+        None => return true,
+    };
+    match tailor_attrs(attrs, version) {
+        Ok(keep) => keep,
+        Err(error) => {
+            *item = Item::Verbatim(error.to_compile_error());
+            true
         }
-        true
-    });
-    result
+    }
 }
 
-pub fn mod_remove_items(item_mod: &mut ItemMod, remove: &str) {
-    if item_mod.content.is_some() {
-        // remove item that has attribute of remove
-        item_mod.content.as_mut().unwrap().1.retain(|item| {
-            let found = match item {
-                Item::Impl(item) => attr::find_attribute(&item.attrs, remove),
-                Item::Trait(item) => attr::find_attribute(&item.attrs, remove),
-                Item::Struct(item) => attr::find_attribute(&item.attrs, remove),
-                Item::Enum(item) => attr::find_attribute(&item.attrs, remove),
-                Item::Fn(item) => attr::find_attribute(&item.attrs, remove),
-                Item::Mod(item) => attr::find_attribute(&item.attrs, remove),
-                _ => false,
-            };
-            !found
-        });
-        // recursively remove item under mod
-        item_mod
-            .content
-            .as_mut()
-            .unwrap()
-            .1
-            .iter_mut()
-            .for_each(|item| {
-                if let Item::Mod(child_item_mod) = item {
-                    mod_remove_items(child_item_mod, remove)
-                }
-            })
-    }
-}
-
-pub fn mod_remove_attr(item_mod: &mut ItemMod, preserve: &str) {
-    // remove attr that has attribute of preserve
-    if item_mod.content.is_some() {
-        item_mod
-            .content
-            .as_mut()
-            .unwrap()
-            .1
-            .iter_mut()
-            .for_each(|item| {
-                match item {
-                    Item::Impl(item) => {
-                        attr::remove_matched_attribute(&mut item.attrs, "amphi", preserve);
-                    }
-                    Item::Trait(item) => {
-                        attr::remove_matched_attribute(&mut item.attrs, "amphi", preserve);
-                    }
-                    Item::Struct(item) => {
-                        attr::remove_matched_attribute(&mut item.attrs, "amphi", preserve);
-                    }
-                    Item::Enum(item) => {
-                        attr::remove_matched_attribute(&mut item.attrs, "amphi", preserve);
-                    }
-                    Item::Fn(item) => {
-                        attr::remove_matched_attribute(&mut item.attrs, "amphi", preserve);
-                    }
-                    Item::Mod(item) => {
-                        attr::remove_matched_attribute(&mut item.attrs, "amphi", preserve);
-                    }
-                    _ => (),
-                };
-            });
-        // recursively remove attributes of items under mod
-        item_mod
-            .content
-            .as_mut()
-            .unwrap()
-            .1
-            .iter_mut()
-            .for_each(|item| {
-                if let Item::Mod(child_item_mod) = item {
-                    mod_remove_attr(child_item_mod, preserve)
-                }
-            })
+/// Keep the items of a mod whose tags hold for `version`.
+pub fn tailor_items(items: &mut Vec<Item>, version: Version) {
+    items.retain_mut(|item| tailor_item(item, version));
+}
+
+/// code whose tags are evaluated in place, as an element of a list
+pub trait Tagged {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>>;
+}
+
+impl Tagged for ImplItem {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match self {
+            ImplItem::Const(item) => Some(&mut item.attrs),
+            ImplItem::Method(item) => Some(&mut item.attrs),
+            ImplItem::Type(item) => Some(&mut item.attrs),
+            ImplItem::Macro(item) => Some(&mut item.attrs),
+            _ => None,
+        }
+    }
+}
+
+impl Tagged for TraitItem {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match self {
+            TraitItem::Const(item) => Some(&mut item.attrs),
+            TraitItem::Method(item) => Some(&mut item.attrs),
+            TraitItem::Type(item) => Some(&mut item.attrs),
+            TraitItem::Macro(item) => Some(&mut item.attrs),
+            _ => None,
+        }
+    }
+}
+
+impl Tagged for Stmt {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match self {
+            Stmt::Local(local) => Some(&mut local.attrs),
+            Stmt::Item(item) => item_attrs(item),
+            Stmt::Expr(expr) | Stmt::Semi(expr, _) => expr.attrs_mut(),
+        }
+    }
+}
+
+impl Tagged for Expr {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match self {
+            Expr::Array(expr) => Some(&mut expr.attrs),
+            Expr::Assign(expr) => Some(&mut expr.attrs),
+            Expr::AssignOp(expr) => Some(&mut expr.attrs),
+            Expr::Async(expr) => Some(&mut expr.attrs),
+            Expr::Await(expr) => Some(&mut expr.attrs),
+            Expr::Binary(expr) => Some(&mut expr.attrs),
+            Expr::Block(expr) => Some(&mut expr.attrs),
+            Expr::Box(expr) => Some(&mut expr.attrs),
+            Expr::Break(expr) => Some(&mut expr.attrs),
+            Expr::Call(expr) => Some(&mut expr.attrs),
+            Expr::Cast(expr) => Some(&mut expr.attrs),
+            Expr::Closure(expr) => Some(&mut expr.attrs),
+            Expr::Continue(expr) => Some(&mut expr.attrs),
+            Expr::Field(expr) => Some(&mut expr.attrs),
+            Expr::ForLoop(expr) => Some(&mut expr.attrs),
+            Expr::Group(expr) => Some(&mut expr.attrs),
+            Expr::If(expr) => Some(&mut expr.attrs),
+            Expr::Index(expr) => Some(&mut expr.attrs),
+            Expr::Let(expr) => Some(&mut expr.attrs),
+            Expr::Lit(expr) => Some(&mut expr.attrs),
+            Expr::Loop(expr) => Some(&mut expr.attrs),
+            Expr::Macro(expr) => Some(&mut expr.attrs),
+            Expr::Match(expr) => Some(&mut expr.attrs),
+            Expr::MethodCall(expr) => Some(&mut expr.attrs),
+            Expr::Paren(expr) => Some(&mut expr.attrs),
+            Expr::Path(expr) => Some(&mut expr.attrs),
+            Expr::Range(expr) => Some(&mut expr.attrs),
+            Expr::Reference(expr) => Some(&mut expr.attrs),
+            Expr::Repeat(expr) => Some(&mut expr.attrs),
+            Expr::Return(expr) => Some(&mut expr.attrs),
+            Expr::Struct(expr) => Some(&mut expr.attrs),
+            Expr::Try(expr) => Some(&mut expr.attrs),
+            Expr::TryBlock(expr) => Some(&mut expr.attrs),
+            Expr::Tuple(expr) => Some(&mut expr.attrs),
+            Expr::Type(expr) => Some(&mut expr.attrs),
+            Expr::Unary(expr) => Some(&mut expr.attrs),
+            Expr::Unsafe(expr) => Some(&mut expr.attrs),
+            Expr::While(expr) => Some(&mut expr.attrs),
+            Expr::Yield(expr) => Some(&mut expr.attrs),
+            _ => None,
+        }
+    }
+}
+
+impl Tagged for FieldValue {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(&mut self.attrs)
+    }
+}
+
+impl Tagged for Field {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(&mut self.attrs)
+    }
+}
+
+impl Tagged for Variant {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(&mut self.attrs)
+    }
+}
+
+impl Tagged for Arm {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(&mut self.attrs)
     }
 }
+
+impl Tagged for FnArg {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match self {
+            FnArg::Receiver(receiver) => Some(&mut receiver.attrs),
+            FnArg::Typed(arg) => Some(&mut arg.attrs),
+        }
+    }
+}
+
+impl Tagged for GenericParam {
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match self {
+            GenericParam::Type(param) => Some(&mut param.attrs),
+            GenericParam::Lifetime(param) => Some(&mut param.attrs),
+            GenericParam::Const(param) => Some(&mut param.attrs),
+        }
+    }
+}
+
+/// Whether an element is kept in `version`. An element with a malformed tag
+/// is kept, and the error pushed to `errors`.
+fn tailor_element<T: Tagged>(
+    element: &mut T,
+    version: Version,
+    errors: &mut Vec<syn::Error>,
+) -> bool {
+    let attrs = match element.attrs_mut() {
+        Some(attrs) => attrs,
+        None => return true,
+    };
+    tailor_attrs(attrs, version).unwrap_or_else(|error| {
+        errors.push(error);
+        true
+    })
+}
+
+/// Keep the elements whose tags hold for `version`, like statements, impl
+/// items or match arms.
+pub fn tailor_elements<T: Tagged>(
+    elements: &mut Vec<T>,
+    version: Version,
+    errors: &mut Vec<syn::Error>,
+) {
+    elements.retain_mut(|element| tailor_element(element, version, errors));
+}
+
+/// Keep the elements of a comma separated list whose tags hold for
+/// `version`, like fields, variants, parameters or arguments.
+pub fn tailor_punctuated<T: Tagged, P: Default>(
+    elements: &mut Punctuated<T, P>,
+    version: Version,
+    errors: &mut Vec<syn::Error>,
+) {
+    *elements = std::mem::take(elements)
+        .into_iter()
+        .filter_map(|mut element| tailor_element(&mut element, version, errors).then_some(element))
+        .collect();
+}
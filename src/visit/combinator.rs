@@ -0,0 +1,297 @@
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::Parser, parse_quote, punctuated::Punctuated, Block, Error, Expr, Item, Macro, Path,
+    Stmt, Token,
+};
+
+use crate::visit::error_expr;
+use crate::visit::preset::needs_std;
+use crate::visit::Join;
+
+/// crates providing the macros lowered here, in addition to plain `join!`
+const MACRO_CRATES: &[&str] = &["futures", "futures_util", "tokio", "std", "core"];
+
+/// The name of a macro provided by one of `MACRO_CRATES`.
+fn macro_name(mac: &Macro) -> Option<String> {
+    let first = mac.path.segments.first()?;
+    if mac.path.segments.len() > 1 && !MACRO_CRATES.contains(&first.ident.to_string().as_str()) {
+        return None;
+    }
+    Some(mac.path.segments.last()?.ident.to_string())
+}
+
+/// whether a macro invocation is lowered as an expression here
+pub fn is_combinator_macro(mac: &Macro) -> bool {
+    matches!(
+        macro_name(mac).as_deref(),
+        Some("join" | "try_join" | "pin" | "select" | "select_biased")
+    )
+}
+
+/// whether a macro invocation pins futures in place, like
+/// `futures::pin_mut!(fut)` or `tokio::pin!(fut)`
+fn is_pinning_macro(mac: &Macro) -> bool {
+    matches!(macro_name(mac).as_deref(), Some("pin_mut" | "pin"))
+}
+
+/// The name of a function of the `future` module, like `futures::future::ready`
+/// or `ready` imported from it.
+fn future_fn(path: &Path, imports: &[(Ident, Ident)]) -> Option<String> {
+    if let Some(ident) = path.get_ident() {
+        if let Some((_, func)) = imports.iter().rev().find(|(name, _)| name == ident) {
+            return Some(func.to_string());
+        }
+    }
+    let mut segments = path.segments.iter().rev();
+    match (segments.next(), segments.next()) {
+        (Some(func), Some(module)) if module.ident == "future" => Some(func.ident.to_string()),
+        // `join_all` and `try_join_all` are distinct enough to be imported
+        (Some(func), _) if func.ident == "join_all" || func.ident == "try_join_all" => {
+            Some(func.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Remove pinning statements, since values do not need to be pinned.
+///
+/// `pin_mut!(fut);` is dropped, while `tokio::pin! { let fut = make(); }`
+/// keeps the declared bindings.
+pub fn remove_pinning(stmts: &mut Vec<Stmt>) {
+    let old = std::mem::take(stmts);
+    for stmt in old {
+        let mac = match &stmt {
+            Stmt::Item(Item::Macro(item)) if item.ident.is_none() => &item.mac,
+            Stmt::Semi(Expr::Macro(expr), _) => &expr.mac,
+            _ => {
+                stmts.push(stmt);
+                continue;
+            }
+        };
+        if !is_pinning_macro(mac) {
+            stmts.push(stmt);
+            continue;
+        }
+        if let Ok(locals) = Block::parse_within.parse2(mac.tokens.clone()) {
+            if locals.iter().all(|stmt| matches!(stmt, Stmt::Local(_))) {
+                stmts.extend(locals);
+            }
+        }
+    }
+}
+
+/// `(a, b)` from the futures given to `join!(a, b)`
+fn join(futures: &[&Expr]) -> Expr {
+    parse_quote!((#(#futures,)*))
+}
+
+/// `(a, b)` from the futures given to `join!(a, b)`, each run on its own
+/// scoped thread. A panic in a thread is resumed in the caller.
+fn join_threads(futures: &[&Expr]) -> Expr {
+    let handles: Vec<_> = (0..futures.len())
+        .map(|i| format_ident!("__amphi_{}", i))
+        .collect();
+    parse_quote!(::std::thread::scope(|__amphi_scope| {
+        #(let #handles = __amphi_scope.spawn(|| #futures);)*
+        (#(match #handles.join() {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(panic) => ::std::panic::resume_unwind(panic),
+        },)*)
+    }))
+}
+
+/// `Ok((a, b))` from the futures given to `try_join!(a, b)`, each run on its
+/// own scoped thread, returning the first error in order.
+fn try_join_threads(futures: &[&Expr]) -> Expr {
+    let results: Vec<Expr> = (0..futures.len())
+        .map(|i| {
+            let result = format_ident!("__amphi_result_{}", i);
+            parse_quote!(#result)
+        })
+        .collect();
+    let joined = join_threads(futures);
+    let matched = try_join(&results.iter().collect::<Vec<_>>());
+    parse_quote!({
+        let (#(#results,)*) = #joined;
+        #matched
+    })
+}
+
+/// The outputs of `join_all(iter.map(f))`, with `f` called on its own scoped
+/// thread for each item. Other futures are already computed once converted,
+/// so they are only collected.
+fn join_all_threads(iter: &Expr, collection: TokenStream2) -> Expr {
+    let (items, f) = match iter {
+        Expr::MethodCall(call) if call.method == "map" && call.args.len() == 1 => {
+            (&call.receiver, &call.args[0])
+        }
+        _ => {
+            return parse_quote!(
+                ::core::iter::IntoIterator::into_iter(#iter).collect::<#collection>()
+            )
+        }
+    };
+    // `(0..n).map(f)` would leave useless parentheses
+    let items = match &**items {
+        Expr::Paren(paren) => &paren.expr,
+        items => items,
+    };
+    parse_quote!({
+        let __amphi_f = #f;
+        ::std::thread::scope(|__amphi_scope| {
+            let __amphi_f = &__amphi_f;
+            let __amphi_handles = ::core::iter::IntoIterator::into_iter(#items)
+            .map(|item| __amphi_scope.spawn(move || __amphi_f(item)))
+            .collect::<::std::vec::Vec<_>>();
+        __amphi_handles
+            .into_iter()
+            .map(|handle| match handle.join() {
+                ::core::result::Result::Ok(value) => value,
+                ::core::result::Result::Err(panic) => ::std::panic::resume_unwind(panic),
+            })
+            .collect::<#collection>()
+        })
+    })
+}
+
+/// `Ok((a, b))` from the futures given to `try_join!(a, b)`, returning the
+/// first error in order.
+fn try_join(futures: &[&Expr]) -> Expr {
+    let values: Vec<_> = (0..futures.len())
+        .map(|i| format_ident!("__amphi_{}", i))
+        .collect();
+    let mut expr: Expr = parse_quote!(::core::result::Result::Ok((#(#values,)*)));
+    for (future, value) in futures.iter().zip(&values).rev() {
+        expr = parse_quote!(match #future {
+            ::core::result::Result::Ok(#value) => #expr,
+            ::core::result::Result::Err(error) => ::core::result::Result::Err(error),
+        });
+    }
+    expr
+}
+
+/// a compile error in place of an expression that cannot be lowered
+fn unsupported(tokens: impl ToTokens, name: &str) -> Expr {
+    let error = Error::new_spanned(
+        tokens,
+        format!("`{}` cannot be converted to blocking code", name),
+    );
+    error_expr(&error)
+}
+
+/// macro arguments parsed as comma separated expressions
+fn parse_args(tokens: TokenStream2) -> Option<Vec<Expr>> {
+    Punctuated::<Expr, Token![,]>::parse_terminated
+        .parse2(tokens)
+        .ok()
+        .map(|args| args.into_iter().collect())
+}
+
+/// Lower `futures` combinators into their sequential blocking equivalents.
+///
+/// - `join!(a, b)` and `future::join(a, b)` to `(a, b)`
+/// - `try_join!(a, b)` and `future::try_join(a, b)` to `Ok((a, b))`, or the
+///   first error
+/// - `join_all(iter)` to a `Vec` of the outputs, `try_join_all(iter)` to a
+///   `Result` of it
+/// - `future::ready(x)` to `x`, `future::ok(x)` and `future::err(e)` to `Ok(x)`
+///   and `Err(e)`
+/// - `pin!(fut)` to `fut`
+///
+/// With `join = "thread"`, joined futures run on scoped threads instead of one
+/// after another. Racing futures, like `select!`, has no blocking equivalent
+/// and is replaced by a compile error.
+///
+/// Functions imported from `futures::future` are recognised by the name they
+/// are imported as, given in `imports`. Scoped threads and the `Vec` of
+/// `join_all` need `std`, so they are errors when the preset `no_std` keeps
+/// the blocking version `no_std`.
+pub fn convert_expr(node: &mut Expr, mode: Join, imports: &[(Ident, Ident)], no_std: Option<&str>) {
+    let threaded = mode == Join::Thread;
+    let converted = match node {
+        Expr::Macro(expr) => {
+            let name = match macro_name(&expr.mac) {
+                Some(name) => name,
+                None => return,
+            };
+            if name == "select" || name == "select_biased" {
+                *node = unsupported(&expr.mac.path, &format!("{}!", name));
+                return;
+            }
+            let args = match parse_args(expr.mac.tokens.clone()) {
+                Some(args) => args,
+                None => return,
+            };
+            if let (true, Some(preset), "join" | "try_join") = (threaded, no_std, name.as_str()) {
+                let what = format!("`{}!` with `join = \"thread\"`", name);
+                *node = error_expr(&needs_std(&expr.mac.path, &what, preset));
+                return;
+            }
+            let args: Vec<&Expr> = args.iter().collect();
+            match (name.as_str(), args.as_slice()) {
+                ("join", futures) if threaded => join_threads(futures),
+                ("join", futures) => join(futures),
+                ("try_join", futures) if threaded => try_join_threads(futures),
+                ("try_join", futures) => try_join(futures),
+                ("pin", [future]) => (*future).clone(),
+                _ => return,
+            }
+        }
+        Expr::Call(call) => {
+            let name = match &*call.func {
+                Expr::Path(func) if func.qself.is_none() => match future_fn(&func.path, imports) {
+                    Some(name) => name,
+                    None => return,
+                },
+                _ => return,
+            };
+            let args: Vec<&Expr> = call.args.iter().collect();
+            let joins = name.starts_with("join") || name.starts_with("try_join");
+            if let (true, Some(preset)) = (joins && (threaded || name.ends_with("_all")), no_std) {
+                let what = if name.ends_with("_all") {
+                    format!("`{}`", name)
+                } else {
+                    format!("`future::{}` with `join = \"thread\"`", name)
+                };
+                *node = error_expr(&needs_std(&call.func, &what, preset));
+                return;
+            }
+            match (name.as_str(), args.as_slice()) {
+                ("join" | "join3" | "join4" | "join5", futures) if threaded => {
+                    join_threads(futures)
+                }
+                ("join" | "join3" | "join4" | "join5", futures) => join(futures),
+                ("try_join" | "try_join3" | "try_join4" | "try_join5", futures) if threaded => {
+                    try_join_threads(futures)
+                }
+                ("try_join" | "try_join3" | "try_join4" | "try_join5", futures) => {
+                    try_join(futures)
+                }
+                ("join_all", [iter]) if threaded => {
+                    join_all_threads(iter, quote!(::std::vec::Vec<_>))
+                }
+                ("join_all", [iter]) => parse_quote!(
+                    ::core::iter::IntoIterator::into_iter(#iter)
+                        .collect::<::std::vec::Vec<_>>()
+                ),
+                ("try_join_all", [iter]) if threaded => {
+                    join_all_threads(iter, quote!(::core::result::Result<::std::vec::Vec<_>, _>))
+                }
+                ("try_join_all", [iter]) => parse_quote!(
+                    ::core::iter::IntoIterator::into_iter(#iter)
+                        .collect::<::core::result::Result<::std::vec::Vec<_>, _>>()
+                ),
+                ("ready", [value]) => (*value).clone(),
+                ("ok", [value]) => parse_quote!(::core::result::Result::Ok(#value)),
+                ("err", [error]) => parse_quote!(::core::result::Result::Err(#error)),
+                ("select" | "select_all" | "select_ok", _) => {
+                    unsupported(&call.func, &format!("future::{}", name))
+                }
+                _ => return,
+            }
+        }
+        _ => return,
+    };
+    *node = converted;
+}
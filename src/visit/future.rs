@@ -0,0 +1,263 @@
+use proc_macro2::Ident;
+use syn::{
+    visit_mut::{self, VisitMut},
+    Block, Expr, ExprBlock, GenericArgument, GenericParam, Generics, Path, PathArguments,
+    ReturnType, Signature, Stmt, TraitBound, Type, TypeParamBound, WherePredicate,
+};
+
+/// `Output` of a `Future<Output = T>` trait bound
+fn future_bound_output<'a, I>(bounds: I) -> Option<Type>
+where
+    I: IntoIterator<Item = &'a TypeParamBound>,
+{
+    bounds.into_iter().find_map(|bound| match bound {
+        TypeParamBound::Trait(bound) => future_trait_output(&bound.path),
+        _ => None,
+    })
+}
+
+/// `T` of a path like `std::future::Future<Output = T>`
+pub fn future_trait_output(path: &Path) -> Option<Type> {
+    let segment = path.segments.last()?;
+    if segment.ident != "Future" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Binding(binding) if binding.ident == "Output" => {
+                Some(binding.ty.clone())
+            }
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// type arguments of the last path segment
+fn type_arguments(path: &Path) -> Vec<&Type> {
+    match path.segments.last().map(|segment| &segment.arguments) {
+        Some(PathArguments::AngleBracketed(args)) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// `T` of `dyn Future<Output = T>`, also behind a `Box` or a reference
+fn pinned_future_output(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::TraitObject(object) => future_bound_output(&object.bounds),
+        Type::Reference(reference) => pinned_future_output(&reference.elem),
+        Type::Paren(paren) => pinned_future_output(&paren.elem),
+        Type::Group(group) => pinned_future_output(&group.elem),
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            match type_arguments(&path.path).as_slice() {
+                [inner] if segment.ident == "Box" => pinned_future_output(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The output type `T` if `ty` is a future type, that is one of
+///
+/// - `impl Future<Output = T> + Send + '_`
+/// - `Pin<Box<dyn Future<Output = T> + Send>>`
+/// - `BoxFuture<'a, T>` or `LocalBoxFuture<'a, T>` from `futures`
+pub fn future_output(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::ImplTrait(impl_trait) => future_bound_output(&impl_trait.bounds),
+        Type::Paren(paren) => future_output(&paren.elem),
+        Type::Group(group) => future_output(&group.elem),
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            let args = type_arguments(&path.path);
+            if segment.ident == "BoxFuture" || segment.ident == "LocalBoxFuture" {
+                args.last().map(|ty| (*ty).clone())
+            } else if segment.ident == "Pin" {
+                args.first().and_then(|ty| pinned_future_output(ty))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// whether a path ends with `Box::pin`
+pub fn is_box_pin(path: &Path) -> bool {
+    let mut segments = path.segments.iter().rev();
+    matches!(
+        (segments.next(), segments.next()),
+        (Some(pin), Some(boxed)) if pin.ident == "pin" && boxed.ident == "Box"
+    )
+}
+
+/// Take the future out of `Box::pin(fut)`, `fut.boxed()` or
+/// `fut.boxed_local()`.
+pub fn unbox_future(expr: &Expr) -> Option<&Expr> {
+    match expr {
+        Expr::Call(call) if call.args.len() == 1 => match &*call.func {
+            Expr::Path(func) if is_box_pin(&func.path) => call.args.first(),
+            _ => None,
+        },
+        Expr::MethodCall(call)
+            if call.args.is_empty() && (call.method == "boxed" || call.method == "boxed_local") =>
+        {
+            Some(&call.receiver)
+        }
+        _ => None,
+    }
+}
+
+/// Replace `Box::pin(async { .. })` by the async block itself. Boxing some
+/// other future is kept as is, since there is no telling whether it is
+/// awaited later.
+pub fn unbox_async_block(expr: &mut Expr) {
+    while let Some(inner) = unbox_future(expr) {
+        if !matches!(inner, Expr::Async(_)) {
+            break;
+        }
+        *expr = inner.clone();
+    }
+}
+
+/// Inline the trailing async block of a function returning a future.
+///
+/// The async block produces the return value of such a function, so `?` and
+/// `return` inside it keep their meaning once the block is inlined. Any other
+/// future boxed in tail position, like `Box::pin(other())`, is the return
+/// value as well and gets unboxed.
+pub fn inline_async_body(sig: &Signature, block: &mut Block) {
+    let returns_future = match &sig.output {
+        ReturnType::Type(_, ty) => future_output(ty).is_some(),
+        ReturnType::Default => false,
+    };
+    if !returns_future {
+        return;
+    }
+    let single = block.stmts.len() == 1;
+    if let Some(Stmt::Expr(tail)) = block.stmts.last_mut() {
+        while let Some(inner) = unbox_future(tail) {
+            *tail = inner.clone();
+        }
+        if let Expr::Async(body) = tail {
+            if single && body.attrs.is_empty() {
+                // the async block is the whole function body
+                *block = body.block.clone();
+            } else {
+                *tail = Expr::Block(ExprBlock {
+                    attrs: body.attrs.clone(),
+                    label: None,
+                    block: body.block.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Convert `AsyncFn`, `AsyncFnMut` and `AsyncFnOnce` bounds to `Fn`, `FnMut`
+/// and `FnOnce`, keeping the arguments and the output.
+pub fn convert_async_fn_bound(bound: &mut TraitBound) {
+    if let Some(segment) = bound.path.segments.last_mut() {
+        let name = segment.ident.to_string();
+        if let Some(blocking) = name.strip_prefix("Async") {
+            if blocking == "Fn" || blocking == "FnMut" || blocking == "FnOnce" {
+                segment.ident = Ident::new(blocking, segment.ident.span());
+            }
+        }
+    }
+}
+
+/// Remove the type parameters bounded by `Future<Output = T>`, either inline
+/// or in the where clause, and return them along with their output `T`.
+fn take_future_params(generics: &mut Generics) -> Vec<(Ident, Type)> {
+    let mut futures = vec![];
+    for param in &generics.params {
+        if let GenericParam::Type(param) = param {
+            let output = future_bound_output(&param.bounds).or_else(|| {
+                generics.where_clause.as_ref().and_then(|where_clause| {
+                    where_clause
+                        .predicates
+                        .iter()
+                        .find_map(|predicate| match predicate {
+                            WherePredicate::Type(predicate)
+                                if is_param(&predicate.bounded_ty, &param.ident) =>
+                            {
+                                future_bound_output(&predicate.bounds)
+                            }
+                            _ => None,
+                        })
+                })
+            });
+            if let Some(output) = output {
+                futures.push((param.ident.clone(), output));
+            }
+        }
+    }
+    if futures.is_empty() {
+        return futures;
+    }
+    let is_future = |ident: &Ident| futures.iter().any(|(future, _)| future == ident);
+    generics.params = std::mem::take(&mut generics.params)
+        .into_iter()
+        .filter(|param| !matches!(param, GenericParam::Type(param) if is_future(&param.ident)))
+        .collect();
+    if let Some(where_clause) = &mut generics.where_clause {
+        where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+            .into_iter()
+            .filter(|predicate| match predicate {
+                WherePredicate::Type(predicate) => !futures
+                    .iter()
+                    .any(|(future, _)| is_param(&predicate.bounded_ty, future)),
+                _ => true,
+            })
+            .collect();
+        if where_clause.predicates.is_empty() {
+            generics.where_clause = None;
+        }
+    }
+    futures
+}
+
+/// whether a type is the type parameter `ident`
+fn is_param(ty: &Type, ident: &Ident) -> bool {
+    match ty {
+        Type::Path(path) => path.qself.is_none() && path.path.is_ident(ident),
+        _ => false,
+    }
+}
+
+/// Replace type parameters by the given types.
+struct SubstituteParams<'a>(&'a [(Ident, Type)]);
+
+impl VisitMut for SubstituteParams<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        let substitute = self.0.iter().find(|(param, _)| is_param(ty, param));
+        match substitute {
+            Some((_, output)) => *ty = output.clone(),
+            None => visit_mut::visit_type_mut(self, ty),
+        }
+    }
+}
+
+/// Collapse type parameters bounded by `Future<Output = T>` into `T`, so that
+/// `F: Fn() -> Fut, Fut: Future<Output = T>` becomes `F: Fn() -> T`.
+pub fn collapse_future_params(sig: &mut Signature, block: Option<&mut Block>) {
+    let futures = take_future_params(&mut sig.generics);
+    if futures.is_empty() {
+        return;
+    }
+    let mut substitute = SubstituteParams(&futures);
+    substitute.visit_signature_mut(sig);
+    if let Some(block) = block {
+        substitute.visit_block_mut(block);
+    }
+}
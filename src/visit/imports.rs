@@ -0,0 +1,177 @@
+use proc_macro2::Ident;
+use syn::{punctuated::Pair, Item, Stmt, UseTree};
+
+/// imports that are only needed by the async version, as the crates they are
+/// imported from and the imported name
+const ASYNC_ONLY: &[(&[&str], &str)] = &[
+    (&["async_trait"], "async_trait"),
+    (&["async_recursion"], "async_recursion"),
+    (&["std", "core", "futures", "futures_util"], "Future"),
+    (&["futures", "futures_util"], "BoxFuture"),
+    (&["futures", "futures_util"], "LocalBoxFuture"),
+    (&["futures", "futures_util"], "FutureExt"),
+    (&["futures", "futures_util"], "TryFutureExt"),
+    (&["futures", "futures_util"], "stream"),
+    (&["futures", "futures_util", "futures_core"], "Stream"),
+    (&["futures", "futures_util", "futures_core"], "TryStream"),
+    (&["futures", "futures_util"], "StreamExt"),
+    (&["futures", "futures_util"], "TryStreamExt"),
+    (&["futures", "futures_util"], "SinkExt"),
+    (&["futures", "futures_util"], "BoxStream"),
+    (&["futures", "futures_util"], "LocalBoxStream"),
+    (&["futures", "futures_util"], "future"),
+    (&["futures", "futures_util", "tokio"], "join"),
+    (&["futures", "futures_util", "tokio"], "try_join"),
+    (&["futures", "futures_util"], "join_all"),
+    (&["futures", "futures_util"], "try_join_all"),
+    (&["futures", "futures_util"], "ready"),
+    (&["futures", "futures_util"], "pin_mut"),
+    (&["std", "core", "tokio"], "pin"),
+    (&["tracing", "tracing_futures"], "Instrument"),
+    (&["trait_variant"], "make"),
+    (&["async_stream"], "stream"),
+    (&["async_stream"], "try_stream"),
+];
+
+/// whether an import is only needed by the async version
+pub fn is_async_only(path: &[String]) -> bool {
+    match (path.first(), path.last()) {
+        (Some(krate), Some(name)) if path.len() > 1 => ASYNC_ONLY
+            .iter()
+            .any(|(crates, async_only)| crates.contains(&krate.as_str()) && name == async_only),
+        _ => false,
+    }
+}
+
+/// Remove imported names whose full path satisfies `matches` from a use tree.
+///
+/// Return `false` if nothing is left in the tree.
+fn prune_use_tree<F>(tree: &mut UseTree, prefix: &mut Vec<String>, matches: &F) -> bool
+where
+    F: Fn(&[String]) -> bool,
+{
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            let keep = prune_use_tree(&mut path.tree, prefix, matches);
+            prefix.pop();
+            keep
+        }
+        // `self` in `use futures::stream::{self, StreamExt}` imports the prefix
+        UseTree::Name(name) if name.ident == "self" => !matches(prefix),
+        UseTree::Name(name) => {
+            prefix.push(name.ident.to_string());
+            let keep = !matches(prefix);
+            prefix.pop();
+            keep
+        }
+        UseTree::Rename(rename) => {
+            prefix.push(rename.ident.to_string());
+            let keep = !matches(prefix);
+            prefix.pop();
+            keep
+        }
+        UseTree::Group(group) => {
+            let items = std::mem::take(&mut group.items);
+            group.items = items
+                .into_pairs()
+                .filter_map(|pair| {
+                    let (mut tree, punct) = pair.into_tuple();
+                    if prune_use_tree(&mut tree, prefix, matches) {
+                        Some(punctuated_pair(tree, punct))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            !group.items.is_empty()
+        }
+        UseTree::Glob(_) => true,
+    }
+}
+
+fn punctuated_pair<T, P>(value: T, punct: Option<P>) -> Pair<T, P> {
+    match punct {
+        Some(punct) => Pair::Punctuated(value, punct),
+        None => Pair::End(value),
+    }
+}
+
+/// whether a use item still imports anything after pruning
+fn prune_item(item: &mut Item, matches: &impl Fn(&[String]) -> bool) -> bool {
+    match item {
+        Item::Use(item_use) => prune_use_tree(&mut item_use.tree, &mut vec![], matches),
+        _ => true,
+    }
+}
+
+/// Remove imports that are no longer needed from the items of a module.
+pub fn remove_imports<F>(items: &mut Vec<Item>, matches: F)
+where
+    F: Fn(&[String]) -> bool,
+{
+    items.retain_mut(|item| prune_item(item, &matches));
+}
+
+/// Remove imports that are no longer needed from the statements of a block.
+pub fn remove_stmt_imports<F>(stmts: &mut Vec<Stmt>, matches: F)
+where
+    F: Fn(&[String]) -> bool,
+{
+    stmts.retain_mut(|stmt| match stmt {
+        Stmt::Item(item) => prune_item(item, &matches),
+        _ => true,
+    });
+}
+
+/// Collect the functions imported from the `future` module of `futures`.
+struct FutureImports {
+    prefix: Vec<String>,
+    found: Vec<(Ident, Ident)>,
+}
+
+impl FutureImports {
+    fn collect(&mut self, tree: &UseTree) {
+        let from_future = matches!(
+            self.prefix.as_slice(),
+            [krate, module] if (krate == "futures" || krate == "futures_util") && module == "future"
+        );
+        match tree {
+            UseTree::Path(path) => {
+                self.prefix.push(path.ident.to_string());
+                self.collect(&path.tree);
+                self.prefix.pop();
+            }
+            UseTree::Group(group) => group.items.iter().for_each(|tree| self.collect(tree)),
+            UseTree::Name(name) if from_future && name.ident != "self" => {
+                self.found.push((name.ident.clone(), name.ident.clone()))
+            }
+            UseTree::Rename(rename) if from_future => self
+                .found
+                .push((rename.rename.clone(), rename.ident.clone())),
+            _ => {}
+        }
+    }
+}
+
+/// Functions imported from `futures::future` by the `use` items among
+/// `items`, like `ready` in `use futures::future::{ready, FutureExt}`, as the
+/// name they are called by and the name of the function.
+///
+/// The imports of nested modules and blocks are left out, since they are only
+/// in scope there.
+pub fn future_imports<'a, I>(items: I) -> Vec<(Ident, Ident)>
+where
+    I: IntoIterator<Item = &'a Item>,
+{
+    let mut imports = FutureImports {
+        prefix: vec![],
+        found: vec![],
+    };
+    for item in items {
+        if let Item::Use(item_use) = item {
+            imports.collect(&item_use.tree);
+        }
+    }
+    imports.found
+}
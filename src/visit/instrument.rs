@@ -0,0 +1,41 @@
+use syn::{parse_quote, Expr, ExprMethodCall};
+
+/// `fut.instrument(span)` of `tracing::Instrument`
+fn instrument_call(expr: &Expr) -> Option<&ExprMethodCall> {
+    match expr {
+        Expr::MethodCall(call) if call.method == "instrument" && call.args.len() == 1 => Some(call),
+        _ => None,
+    }
+}
+
+/// Enter the span instead of instrumenting the future.
+///
+/// `fut.instrument(span).await` becomes `span.in_scope(|| fut)`, and an
+/// instrumented async block becomes the closure itself. The current span is
+/// entered anyway while running blocking code, so `fut.in_current_span()`
+/// becomes `fut`.
+pub fn convert_expr(node: &mut Expr) {
+    while let Expr::MethodCall(call) = node {
+        if call.method != "in_current_span" || !call.args.is_empty() {
+            break;
+        }
+        *node = (*call.receiver).clone();
+    }
+    let call = match node {
+        Expr::Await(expr) => match instrument_call(&expr.base) {
+            Some(call) => call,
+            None => return,
+        },
+        _ => return,
+    };
+    let fut = &*call.receiver;
+    let span = &call.args[0];
+    *node = match fut {
+        Expr::Async(block) => {
+            let capture = block.capture;
+            let block = &block.block;
+            parse_quote!(#span.in_scope(#capture || #block))
+        }
+        fut => parse_quote!(#span.in_scope(|| #fut.await)),
+    };
+}
@@ -0,0 +1,96 @@
+use syn::{
+    parse_quote,
+    visit::{self, Visit},
+    Block, Expr, ExprAsync, ExprBlock, ExprCall, ExprClosure, ExprReturn, ExprTry, Item, Macro,
+    Stmt,
+};
+
+use super::macros::escapes_tokens;
+
+/// Find `?` and `return` that belong to the block itself, i.e. not the ones
+/// inside nested closures, async blocks or items.
+struct ControlFlow {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for ControlFlow {
+    fn visit_expr_try(&mut self, node: &'ast ExprTry) {
+        self.found = true;
+        visit::visit_expr_try(self, node);
+    }
+
+    fn visit_expr_return(&mut self, node: &'ast ExprReturn) {
+        self.found = true;
+        visit::visit_expr_return(self, node);
+    }
+
+    // `?` and `return` inside a closure exit the closure only
+    fn visit_expr_closure(&mut self, _node: &'ast ExprClosure) {}
+
+    // `?` and `return` inside an async block exit the async block only
+    fn visit_expr_async(&mut self, _node: &'ast ExprAsync) {}
+
+    fn visit_item(&mut self, _node: &'ast Item) {}
+
+    // macro arguments are not parsed, so any `?` or `return` in them counts
+    fn visit_macro(&mut self, node: &'ast Macro) {
+        self.found |= escapes_tokens(&node.tokens);
+    }
+}
+
+/// whether `?` or `return` in block would exit the enclosing function once
+/// the block is inlined
+pub fn escapes_block(block: &Block) -> bool {
+    let mut control_flow = ControlFlow { found: false };
+    control_flow.visit_block(block);
+    control_flow.found
+}
+
+/// Lower an async block into its blocking equivalent.
+///
+/// An async block is a boundary for `?` and `return`, and `async move` takes
+/// ownership of what it captures. Such blocks become an immediately invoked
+/// closure, which keeps both the control flow and the capture mode. Other
+/// blocks are inlined as is.
+pub fn lower_async_block(expr: &ExprAsync) -> Expr {
+    let ExprAsync {
+        attrs,
+        capture,
+        block,
+        ..
+    } = expr;
+    if capture.is_some() || escapes_block(block) {
+        let mut call: ExprCall = parse_quote!((#capture || #block)());
+        call.attrs = attrs.clone();
+        return Expr::Call(call);
+    }
+    match block.stmts.as_slice() {
+        // remove useless braces when there is only a tail expression
+        [Stmt::Expr(inner)] if attrs.is_empty() => inner.clone(),
+        _ => Expr::Block(ExprBlock {
+            attrs: attrs.clone(),
+            block: block.clone(),
+            label: None,
+        }),
+    }
+}
+
+/// Lower an async closure, or a closure returning an async block, into a
+/// plain closure.
+///
+/// `async move |x| body` becomes `move |x| body`. For `|x| async move { body
+/// }`, the async block becomes the closure body, and the closure takes over
+/// the capture mode of the async block.
+pub fn lower_async_closure(closure: &mut ExprClosure) {
+    closure.asyncness = None;
+    if let Expr::Async(body) = &*closure.body {
+        if closure.capture.is_none() {
+            closure.capture = body.capture;
+        }
+        *closure.body = Expr::Block(ExprBlock {
+            attrs: body.attrs.clone(),
+            block: body.block.clone(),
+            label: None,
+        });
+    }
+}
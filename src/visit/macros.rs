@@ -0,0 +1,183 @@
+use proc_macro2::{Delimiter, Group, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{
+    parse::{ParseStream, Parser},
+    punctuated::Punctuated,
+    visit_mut::VisitMut,
+    Expr, Macro, Token,
+};
+
+use crate::visit::Options;
+
+/// std macros whose arguments are expressions
+const STD_MACROS: &[&str] = &[
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "dbg",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "eprint",
+    "eprintln",
+    "format",
+    "format_args",
+    "matches",
+    "panic",
+    "print",
+    "println",
+    "todo",
+    "unimplemented",
+    "unreachable",
+    "vec",
+    "write",
+    "writeln",
+];
+
+/// whether the arguments of a macro invocation should be converted
+pub fn is_convertible(mac: &Macro, options: &Options) -> bool {
+    match mac.path.segments.last() {
+        Some(segment) => {
+            let name = segment.ident.to_string();
+            STD_MACROS.contains(&name.as_str()) || options.macros.contains(&name)
+        }
+        None => false,
+    }
+}
+
+/// whether a macro invocation is a `macro_rules!` declaration
+pub fn is_macro_rules(mac: &Macro) -> bool {
+    mac.path.is_ident("macro_rules")
+}
+
+/// Arguments of a macro invocation, parsed as expressions
+enum MacroArgs {
+    /// `format!("{}", a, b = c)`
+    List(Punctuated<Expr, Token![,]>),
+    /// `vec![elem; n]`
+    Repeat(Box<Expr>, Token![;], Box<Expr>),
+    /// `matches!(expr, pattern)`, where only the leading expression is
+    /// converted
+    Leading(Box<Expr>, Token![,], TokenStream2),
+}
+
+impl MacroArgs {
+    fn parse(tokens: TokenStream2) -> Option<Self> {
+        let list = Punctuated::<Expr, Token![,]>::parse_terminated;
+        let repeat = |input: ParseStream| -> syn::Result<Self> {
+            Ok(MacroArgs::Repeat(
+                input.parse()?,
+                input.parse()?,
+                input.parse()?,
+            ))
+        };
+        let leading = |input: ParseStream| -> syn::Result<Self> {
+            Ok(MacroArgs::Leading(
+                input.parse()?,
+                input.parse()?,
+                input.parse()?,
+            ))
+        };
+        list.parse2(tokens.clone())
+            .map(MacroArgs::List)
+            .or_else(|_| repeat.parse2(tokens.clone()))
+            .or_else(|_| leading.parse2(tokens))
+            .ok()
+    }
+
+    fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            MacroArgs::List(list) => list.iter_mut().collect(),
+            MacroArgs::Repeat(elem, _, len) => vec![&mut **elem, &mut **len],
+            MacroArgs::Leading(expr, _, _) => vec![&mut **expr],
+        }
+    }
+}
+
+impl ToTokens for MacroArgs {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            MacroArgs::List(list) => list.to_tokens(tokens),
+            MacroArgs::Repeat(elem, semi, len) => tokens.extend(quote!(#elem #semi #len)),
+            MacroArgs::Leading(expr, comma, rest) => tokens.extend(quote!(#expr #comma #rest)),
+        }
+    }
+}
+
+/// Visit the arguments of a macro invocation as expressions.
+///
+/// Return `false` if the arguments cannot be parsed as expressions, in which
+/// case the macro is left untouched.
+pub fn visit_macro_args<V: VisitMut>(visitor: &mut V, mac: &mut Macro) -> bool {
+    match MacroArgs::parse(mac.tokens.clone()) {
+        Some(mut args) => {
+            for expr in args.exprs_mut() {
+                visitor.visit_expr_mut(expr);
+            }
+            mac.tokens = args.into_token_stream();
+            true
+        }
+        None => false,
+    }
+}
+
+/// whether a token stream contains `?` or `return`
+pub fn escapes_tokens(tokens: &TokenStream2) -> bool {
+    tokens.clone().into_iter().any(|token| match token {
+        TokenTree::Punct(punct) => punct.as_char() == '?',
+        TokenTree::Ident(ident) => ident == "return",
+        TokenTree::Group(group) => escapes_tokens(&group.stream()),
+        TokenTree::Literal(_) => false,
+    })
+}
+
+/// Strip `.await` and `async` from raw tokens, for macro arguments that are
+/// not expressions and for `macro_rules!` bodies.
+///
+/// Like their parsed counterparts, async blocks using `?`, `return` or `move`
+/// become an immediately invoked closure, and async closures become plain
+/// closures.
+pub fn strip_async_tokens(tokens: TokenStream2) -> TokenStream2 {
+    let mut output = TokenStream2::new();
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            // `.await`
+            TokenTree::Punct(ref punct)
+                if punct.as_char() == '.'
+                    && matches!(iter.peek(), Some(TokenTree::Ident(ident)) if ident == "await") =>
+            {
+                iter.next();
+            }
+            // `async move? { .. }` and `async move? |..| ..`
+            TokenTree::Ident(ref ident) if ident == "async" => {
+                let capture = match iter.peek() {
+                    Some(TokenTree::Ident(ident)) if ident == "move" => iter.next(),
+                    _ => None,
+                };
+                match iter.peek() {
+                    Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                        let mut body =
+                            Group::new(Delimiter::Brace, strip_async_tokens(group.stream()));
+                        body.set_span(group.span());
+                        iter.next();
+                        if capture.is_some() || escapes_tokens(&body.stream()) {
+                            output.extend(quote!((#capture || #body)()));
+                        } else {
+                            output.extend(Some(TokenTree::Group(body)));
+                        }
+                    }
+                    _ => output.extend(capture),
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut stripped =
+                    Group::new(group.delimiter(), strip_async_tokens(group.stream()));
+                stripped.set_span(group.span());
+                output.extend(Some(TokenTree::Group(stripped)));
+            }
+            token => output.extend(Some(token)),
+        }
+    }
+    output
+}
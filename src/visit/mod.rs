@@ -8,24 +8,75 @@ use syn::{
     parse_quote,
     spanned::Spanned,
     visit_mut::{self, VisitMut},
-    Expr, ExprBlock, File, ImplItem, Item, ItemMod, Stmt, TraitItem, UseTree,
+    Block, Expr, ExprArray, ExprCall, ExprMacro, ExprMatch, ExprMethodCall, ExprStruct, ExprTuple,
+    FieldsNamed, FieldsUnnamed, File, Generics, ImplItem, ImplItemMethod, Item, ItemEnum, ItemFn,
+    ItemImpl, ItemMod, ItemTrait, Macro, Path, Signature, Stmt, TraitBound, TraitItem,
+    TraitItemMethod, Type, UseTree,
 };
 
-use crate::visit::attr::remove_matched_attribute;
+use crate::parse::Replacement;
+use crate::visit::attr::Tagged;
+use crate::visit::preset::Preset;
+use crate::visit::replace::Replacer;
 use crate::Version;
 
 mod attr;
+mod combinator;
+mod future;
+mod imports;
+mod instrument;
+mod lower;
+mod macros;
+pub(crate) mod preset;
+mod replace;
+mod stream;
+mod variant;
+
+const MOD_DECLARE: &str = "declare_mod";
+
+const CANNOT_REMOVE: &str = "The tag does not hold, but only statements, arguments and elements \
+     of arrays, tuples or struct literals can be removed";
+
+/// An expression reporting `error`. The braces of `compile_error! { .. }`
+/// would end a statement it starts, so it is wrapped in parentheses.
+pub fn error_expr(error: &syn::Error) -> Expr {
+    let error = error.to_compile_error();
+    parse_quote!((#error))
+}
 
-const MOD_DECLARE: &'static str = "declare_mod";
+/// how the futures given to `join!` and friends run in the blocking version
+#[derive(Clone, Copy, PartialEq, Default)]
+pub(crate) enum Join {
+    /// one after another
+    #[default]
+    Sequential,
+    /// each on its own scoped thread
+    Thread,
+}
 
-macro_rules! tailor_expr {
-    ($attrs:expr, $preserve:expr, $remove: expr, $node:expr) => {{
-        remove_matched_attribute(&mut $attrs, "amphi", $preserve);
-        if remove_matched_attribute(&mut $attrs, "amphi", $remove).is_some() {
-            // remove expression
-            *$node = Expr::Verbatim(quote! {});
-        }
-    }};
+/// options given as arguments of `#[amphi(...)]`
+#[derive(Clone, Default)]
+pub(crate) struct Options {
+    /// user macros whose arguments are converted like the ones of std macros
+    pub macros: Vec<String>,
+    /// whether to strip `.await` and `async` from `macro_rules!` bodies
+    pub macro_rules: bool,
+    /// how to lower `join!`, `try_join!` and `join_all`
+    pub join: Join,
+    /// paths substituted in the blocking version
+    pub replace: Vec<Replacement>,
+    /// presets converting well known async crates
+    pub presets: Vec<&'static Preset>,
+}
+
+impl Options {
+    /// the name of the preset keeping the blocking version `no_std`, if any
+    pub fn no_std(&self) -> Option<&'static str> {
+        self.presets
+            .iter()
+            .find(|preset| preset.no_std)
+            .map(|preset| preset.name)
+    }
 }
 
 /// replace use tree, fill mod declaration with implementation,
@@ -36,6 +87,12 @@ pub(crate) struct AmphiConversion {
     mod_name: String,
     /// path
     path: Option<PathBuf>,
+    /// conversion options
+    options: Options,
+    /// substitution of the `replace(..)` paths, for the blocking version only
+    replacer: Option<Replacer>,
+    /// malformed tags of code that cannot be replaced by the error, like fields
+    tag_errors: Vec<syn::Error>,
 }
 
 impl AmphiConversion {
@@ -43,11 +100,25 @@ impl AmphiConversion {
         version: Version,
         mod_name: T,
         path: P,
+        options: Options,
     ) -> Self {
+        let replacer = match version {
+            Version::Sync => {
+                let mut replacer = Replacer::new(options.replace.clone());
+                for preset in &options.presets {
+                    replacer.add_preset(preset);
+                }
+                Some(replacer)
+            }
+            Version::Async => None,
+        };
         Self {
             version,
             mod_name: mod_name.into(),
             path: path.into(),
+            options,
+            replacer,
+            tag_errors: vec![],
         }
     }
     pub fn convert(&mut self, item: TokenStream2) -> TokenStream2 {
@@ -57,26 +128,28 @@ impl AmphiConversion {
             return syn_error.into();
         }
         self.visit_file_mut(&mut syntax_tree);
-        self.tailor_version(&mut syntax_tree);
-        quote!(#syntax_tree)
+        let errors = self.replacer.as_ref().map(|replacer| replacer.errors());
+        let tag_errors = self.tag_errors.iter().map(syn::Error::to_compile_error);
+        quote!(#syntax_tree #errors #(#tag_errors)*)
     }
 
-    // keep only code that conform to current version (async or sync)
-    fn tailor_version(&self, file: &mut File) {
-        let preserve = self.version.as_str();
-        let remove = match self.version {
-            Version::Async => Version::Sync,
-            Version::Sync => Version::Async,
+    /// Run the function hooks of presets, in the blocking version only.
+    fn convert_fn(&self, sig: &Signature, block: &mut Block) {
+        if let Version::Sync = self.version {
+            for preset in &self.options.presets {
+                (preset.convert_fn)(sig, block);
+            }
         }
-        .as_str();
-        // remove item that violate current version
-        // preserve item that conform to current version, and remove tagging attribute
-        file.items.iter_mut().for_each(|item| {
-            if let Item::Mod(item_mod) = item {
-                attr::mod_remove_items(item_mod, remove);
-                attr::mod_remove_attr(item_mod, preserve);
+    }
+
+    /// Run the checks of presets on a function body before it is converted,
+    /// in the blocking version only.
+    fn check_fn(&self, sig: &Signature, block: &mut Block) {
+        if let Version::Sync = self.version {
+            for preset in &self.options.presets {
+                (preset.check_fn)(sig, block);
             }
-        });
+        }
     }
 
     /// remove all ident to sync or asynchronous according to self.version
@@ -84,24 +157,20 @@ impl AmphiConversion {
         match item {
             // A path prefix of imports in a `use` item: `std::...`.
             UseTree::Path(path) => {
-                self.replace_use_tree(&mut *path.tree);
-                if path.ident.to_string() == self.mod_name {
+                self.replace_use_tree(&mut path.tree);
+                if path.ident == self.mod_name {
                     path.ident = Ident::new(self.version.as_str(), path.span());
                 }
             }
 
             // An identifier imported by a `use` item: `HashMap`.
-            UseTree::Name(name) => {
-                if name.ident.to_string() == self.mod_name {
-                    name.ident = Ident::new(self.version.as_str(), name.span());
-                }
+            UseTree::Name(name) if name.ident == self.mod_name => {
+                name.ident = Ident::new(self.version.as_str(), name.span());
             }
 
             // An renamed identifier imported by a `use` item: `HashMap as Map`.
-            UseTree::Rename(rename) => {
-                if rename.ident.to_string() == self.mod_name {
-                    rename.ident = Ident::new(self.version.as_str(), rename.span());
-                }
+            UseTree::Rename(rename) if rename.ident == self.mod_name => {
+                rename.ident = Ident::new(self.version.as_str(), rename.span());
             }
 
             // A braced group of imports in a `use` item: `{A, B, C}`.
@@ -118,11 +187,10 @@ impl AmphiConversion {
         for item in &mut file.items {
             // iterate inside the amphi mod
             if let Item::Mod(item_mod) = item {
-                if item_mod.content.is_some() {
-                    for i in &mut item_mod.content.as_mut().unwrap().1 {
+                if let Some((_, items)) = &mut item_mod.content {
+                    for i in items {
                         if matches!(i, Item::Macro(_)) {
-                            if self.path.is_some() {
-                                let path = self.path.clone().unwrap();
+                            if let Some(path) = self.path.clone() {
                                 self.macro_mod_declaration(
                                     i,
                                     vec![format!(
@@ -216,191 +284,207 @@ impl AmphiConversion {
 }
 
 impl VisitMut for AmphiConversion {
-    fn visit_item_mut(&mut self, item: &mut Item) {
-        // Delegate to the default impl to visit nested expressions.
-        visit_mut::visit_item_mut(self, item);
-
-        if let Item::Use(item_use) = item {
-            // leading_colon is some indicating using crates
-            if item_use.leading_colon.is_none() {
-                // here is when use amphi mod
-                self.replace_use_tree(&mut item_use.tree);
-            }
+    fn visit_file_mut(&mut self, file: &mut File) {
+        if let Some(replacer) = &mut self.replacer {
+            replacer.replace_imports(&mut file.items);
         }
+        visit_mut::visit_file_mut(self, file);
     }
 
-    fn visit_expr_mut(&mut self, node: &mut Expr) {
-        // Delegate to the default impl to visit nested expressions.
-        visit_mut::visit_expr_mut(self, node);
-
-        let preserve = self.version.as_str();
-        let remove = match self.version {
-            Version::Async => Version::Sync,
-            Version::Sync => Version::Async,
+    fn visit_item_mod_mut(&mut self, item_mod: &mut ItemMod) {
+        // keep only items that conform to current version (async or sync),
+        // before converting them
+        if let Some((_, items)) = &mut item_mod.content {
+            attr::tailor_items(items, self.version);
         }
-        .as_str();
-
-        match node {
-            // A slice literal expression: `[a, b, c, d]`.
-            Expr::Array(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // An assignment expression: `a :&str=compute()`;
-            Expr::Assign(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A compound assignment expression: `counter += 1`.
-            Expr::AssignOp(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // An async block: `async { ... }`.
-            Expr::Async(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // An await expression: `fut.await`.
-            Expr::Await(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A binary operation: `a + b`, `a * b`.
-            Expr::Binary(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A blocked scope: `{ ... }`.
-            Expr::Block(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A box expression: `box f`.
-            Expr::Box(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A `break`, with an optional label to break and an optional expression.
-            Expr::Break(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A function call expression: `invoke(a, b)`.
-            Expr::Call(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A cast expression: `foo as f64`.
-            Expr::Cast(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A closure expression: `|a, b| a + b`.
-            Expr::Closure(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A `continue`, with an optional label.
-            Expr::Continue(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // Access of a named struct field (`obj.k`) or unnamed tuple struct field (`obj.0`).
-            Expr::Field(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A for loop: `for pat in expr { ... }`.
-            Expr::ForLoop(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // An expression contained within invisible delimiters.
-            //
-            // This variant is important for faithfully representing the precedence
-            // of expressions and is related to `None`-delimited spans in a
-            // `TokenStream`.
-            Expr::Group(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // An `if` expression with an optional `else` block: `if expr { ... }
-            // else { ... }`.
-            //
-            // The `else` branch expression may only be an `If` or `Block`
-            // expression, not any of the other types of expression.
-            Expr::If(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A square bracketed indexing expression: `vector[2]`.
-            Expr::Index(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A `let` guard: `let Some(x) = opt`.
-            Expr::Let(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
-
-            // A literal in place of an expression: `1`, `"foo"`.
-            Expr::Lit(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+        if let (Some(replacer), Some((_, items))) = (&mut self.replacer, &mut item_mod.content) {
+            replacer.replace_imports(items);
+        }
+        visit_mut::visit_item_mod_mut(self, item_mod);
+    }
 
-            // Conditionless loop: `loop { ... }`.
-            Expr::Loop(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        attr::tailor_elements(&mut block.stmts, self.version, &mut self.tag_errors);
+        if let Some(replacer) = &mut self.replacer {
+            replacer.replace_stmt_imports(&mut block.stmts);
+        }
+        visit_mut::visit_block_mut(self, block);
+        if let Version::Sync = self.version {
+            for preset in &self.options.presets {
+                (preset.convert_block)(block);
+            }
+        }
+    }
 
-            // A macro invocation expression: `format!("{}", q)`.
-            Expr::Macro(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_item_impl_mut(&mut self, item: &mut ItemImpl) {
+        attr::tailor_elements(&mut item.items, self.version, &mut self.tag_errors);
+        visit_mut::visit_item_impl_mut(self, item);
+    }
 
-            // A `match` expression: `match n { Some(n) => {}, None => {} }`.
-            Expr::Match(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_item_trait_mut(&mut self, item: &mut ItemTrait) {
+        attr::tailor_elements(&mut item.items, self.version, &mut self.tag_errors);
+        visit_mut::visit_item_trait_mut(self, item);
+    }
 
-            // A method call expression: `x.foo::<T>(a, b)`.
-            Expr::MethodCall(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_item_enum_mut(&mut self, item: &mut ItemEnum) {
+        attr::tailor_punctuated(&mut item.variants, self.version, &mut self.tag_errors);
+        visit_mut::visit_item_enum_mut(self, item);
+    }
 
-            // A parenthesized expression: `(a + b)`.
-            Expr::Paren(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_fields_named_mut(&mut self, fields: &mut FieldsNamed) {
+        attr::tailor_punctuated(&mut fields.named, self.version, &mut self.tag_errors);
+        visit_mut::visit_fields_named_mut(self, fields);
+    }
 
-            // A path like `std::mem::replace` possibly containing generic
-            // parameters and a qualified self-type.
-            //
-            // A plain identifier like `x` is a path of length 1.
-            Expr::Path(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_fields_unnamed_mut(&mut self, fields: &mut FieldsUnnamed) {
+        attr::tailor_punctuated(&mut fields.unnamed, self.version, &mut self.tag_errors);
+        visit_mut::visit_fields_unnamed_mut(self, fields);
+    }
 
-            // A range expression: `1..2`, `1..`, `..2`, `1..=2`, `..=2`.
-            Expr::Range(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_signature_mut(&mut self, sig: &mut Signature) {
+        attr::tailor_punctuated(&mut sig.inputs, self.version, &mut self.tag_errors);
+        visit_mut::visit_signature_mut(self, sig);
+    }
 
-            // A referencing operation: `&a` or `&mut a`.
-            Expr::Reference(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_generics_mut(&mut self, generics: &mut Generics) {
+        attr::tailor_punctuated(&mut generics.params, self.version, &mut self.tag_errors);
+        visit_mut::visit_generics_mut(self, generics);
+    }
 
-            // An array literal constructed from one repeated element: `[0u8; N]`.
-            Expr::Repeat(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_expr_call_mut(&mut self, expr: &mut ExprCall) {
+        attr::tailor_punctuated(&mut expr.args, self.version, &mut self.tag_errors);
+        visit_mut::visit_expr_call_mut(self, expr);
+    }
 
-            // A `return`, with an optional value to be returned.
-            Expr::Return(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_expr_method_call_mut(&mut self, expr: &mut ExprMethodCall) {
+        attr::tailor_punctuated(&mut expr.args, self.version, &mut self.tag_errors);
+        visit_mut::visit_expr_method_call_mut(self, expr);
+    }
 
-            // A struct literal expression: `Point { x: 1, y: 1 }`.
-            //
-            // The `rest` provides the value of the remaining fields as in `S { a:
-            // 1, b: 1, ..rest }`.
-            Expr::Struct(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_expr_array_mut(&mut self, expr: &mut ExprArray) {
+        attr::tailor_punctuated(&mut expr.elems, self.version, &mut self.tag_errors);
+        visit_mut::visit_expr_array_mut(self, expr);
+    }
 
-            // A try-expression: `expr?`.
-            Expr::Try(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_expr_tuple_mut(&mut self, expr: &mut ExprTuple) {
+        attr::tailor_punctuated(&mut expr.elems, self.version, &mut self.tag_errors);
+        // `(a,)` stays a tuple
+        if expr.elems.len() == 1 {
+            expr.elems.push_punct(Default::default());
+        }
+        visit_mut::visit_expr_tuple_mut(self, expr);
+    }
 
-            // A try block: `try { ... }`.
-            Expr::TryBlock(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_expr_struct_mut(&mut self, expr: &mut ExprStruct) {
+        attr::tailor_punctuated(&mut expr.fields, self.version, &mut self.tag_errors);
+        visit_mut::visit_expr_struct_mut(self, expr);
+    }
 
-            // A tuple expression: `(a, b, c, d)`.
-            Expr::Tuple(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_expr_match_mut(&mut self, expr: &mut ExprMatch) {
+        attr::tailor_elements(&mut expr.arms, self.version, &mut self.tag_errors);
+        visit_mut::visit_expr_match_mut(self, expr);
+    }
 
-            // A type ascription expression: `foo: f64`.
-            Expr::Type(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_item_fn_mut(&mut self, item: &mut ItemFn) {
+        self.check_fn(&item.sig, &mut item.block);
+        visit_mut::visit_item_fn_mut(self, item);
+        self.convert_fn(&item.sig, &mut item.block);
+    }
 
-            // A unary operation: `!x`, `*x`.
-            Expr::Unary(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.check_fn(&method.sig, &mut method.block);
+        visit_mut::visit_impl_item_method_mut(self, method);
+        self.convert_fn(&method.sig, &mut method.block);
+    }
 
-            // An unsafe block: `unsafe { ... }`.
-            Expr::Unsafe(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_trait_item_method_mut(&mut self, method: &mut TraitItemMethod) {
+        if let Some(block) = &mut method.default {
+            self.check_fn(&method.sig, block);
+        }
+        visit_mut::visit_trait_item_method_mut(self, method);
+        if let Some(block) = &mut method.default {
+            self.convert_fn(&method.sig, block);
+        }
+    }
 
-            // A while loop: `while expr { ... }`.
-            Expr::While(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    // types, expressions and trait bounds
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        if let Some(replacer) = &mut self.replacer {
+            replacer.replace_path(path);
+        }
+        visit_mut::visit_path_mut(self, path);
+    }
 
-            // A yield expression: `yield expr`.
-            Expr::Yield(expr) => tailor_expr!(expr.attrs, preserve, remove, node),
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        // Delegate to the default impl to visit nested expressions.
+        visit_mut::visit_item_mut(self, item);
 
-            _ => {}
+        if let Item::Use(item_use) = item {
+            // leading_colon is some indicating using crates
+            if item_use.leading_colon.is_none() {
+                // here is when use amphi mod
+                self.replace_use_tree(&mut item_use.tree);
+            }
         }
     }
 
-    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        // the tagged expressions left are operands and the like, which cannot
+        // be removed
+        if let Some(attrs) = node.attrs_mut() {
+            match attr::tailor_attrs(attrs, self.version) {
+                Ok(true) => {}
+                Ok(false) => {
+                    let error = syn::Error::new(node.span(), CANNOT_REMOVE);
+                    *node = error_expr(&error);
+                    return;
+                }
+                Err(error) => {
+                    *node = error_expr(&error);
+                    return;
+                }
+            }
+        }
+        if let Some(replacer) = &self.replacer {
+            replacer.check_expr(node);
+        }
         // Delegate to the default impl to visit nested expressions.
-        visit_mut::visit_stmt_mut(self, stmt);
+        visit_mut::visit_expr_mut(self, node);
 
-        let preserve = self.version.as_str();
-        let remove = match self.version {
-            Version::Async => Version::Sync,
-            Version::Sync => Version::Async,
-        }
-        .as_str();
-        match stmt {
-            Stmt::Local(local) => {
-                remove_matched_attribute(&mut local.attrs, "amphi", preserve);
-                if remove_matched_attribute(&mut local.attrs, "amphi", remove).is_some() {
-                    *stmt = Stmt::Expr(Expr::Verbatim(quote! {}));
+        // after the nested paths are replaced, so presets only see the
+        // blocking ones
+        if let Version::Sync = self.version {
+            for preset in &self.options.presets {
+                if let Err(error) = (preset.convert_expr)(node) {
+                    *node = error_expr(&error);
                 }
             }
-            _ => {}
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        if macros::is_convertible(mac, &self.options) {
+            macros::visit_macro_args(self, mac);
         }
     }
 }
 
-pub struct AsyncAwaitRemoval;
+pub struct AsyncAwaitRemoval {
+    /// conversion options
+    options: Options,
+    /// functions imported from `futures::future` in the current scope, by the
+    /// name they are called, innermost last
+    future_imports: Vec<(Ident, Ident)>,
+}
 
 impl AsyncAwaitRemoval {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            future_imports: vec![],
+        }
+    }
+
     pub fn remove_async_await(&mut self, item: TokenStream2) -> TokenStream2 {
         let mut syntax_tree: File = syn::parse(item.into()).unwrap();
         self.visit_file_mut(&mut syntax_tree);
@@ -415,6 +499,7 @@ impl VisitMut for AsyncAwaitRemoval {
 
         match item {
             Item::Impl(item) => {
+                attr::remove_attribute_macro(&mut item.attrs, "async_trait", "async_trait");
                 for inner in &mut item.items {
                     if let ImplItem::Method(ref mut method) = inner {
                         if method.sig.asyncness.is_some() {
@@ -424,6 +509,7 @@ impl VisitMut for AsyncAwaitRemoval {
                 }
             }
             Item::Trait(item) => {
+                attr::remove_attribute_macro(&mut item.attrs, "async_trait", "async_trait");
                 for inner in &mut item.items {
                     if let TraitItem::Method(ref mut method) = inner {
                         if method.sig.asyncness.is_some() {
@@ -432,37 +518,156 @@ impl VisitMut for AsyncAwaitRemoval {
                     }
                 }
             }
-            Item::Fn(item) => {
-                if item.sig.asyncness.is_some() {
-                    item.sig.asyncness = None;
-                }
+            Item::Fn(item) if item.sig.asyncness.is_some() => {
+                item.sig.asyncness = None;
             }
             _ => {}
         }
     }
+    fn visit_file_mut(&mut self, file: &mut File) {
+        self.future_imports = imports::future_imports(&file.items);
+        variant::remove_trait_variants(&mut file.items);
+        visit_mut::visit_file_mut(self, file);
+        imports::remove_imports(&mut file.items, imports::is_async_only);
+    }
+
+    fn visit_item_mod_mut(&mut self, item_mod: &mut ItemMod) {
+        // a module does not see the imports of its parent
+        let mut outer = vec![];
+        if let Some((_, items)) = &mut item_mod.content {
+            outer = std::mem::replace(&mut self.future_imports, imports::future_imports(&*items));
+            variant::remove_trait_variants(items);
+        }
+        visit_mut::visit_item_mod_mut(self, item_mod);
+        if let Some((_, items)) = &mut item_mod.content {
+            imports::remove_imports(items, imports::is_async_only);
+            self.future_imports = outer;
+        }
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        // a block sees the imports around it along with its own
+        let outer = self.future_imports.len();
+        let items = block.stmts.iter().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        });
+        self.future_imports.extend(imports::future_imports(items));
+        // `pin_mut!(fut);` to nothing
+        combinator::remove_pinning(&mut block.stmts);
+        variant::remove_stmt_trait_variants(&mut block.stmts);
+        visit_mut::visit_block_mut(self, block);
+        imports::remove_stmt_imports(&mut block.stmts, imports::is_async_only);
+        self.future_imports.truncate(outer);
+    }
+
+    fn visit_item_fn_mut(&mut self, item: &mut ItemFn) {
+        attr::remove_attribute_macro(&mut item.attrs, "async_recursion", "async_recursion");
+        future::collapse_future_params(&mut item.sig, Some(&mut item.block));
+        future::inline_async_body(&item.sig, &mut item.block);
+        stream::box_tail(&item.sig, &mut item.block, self.options.no_std());
+        visit_mut::visit_item_fn_mut(self, item);
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        attr::remove_attribute_macro(&mut method.attrs, "async_recursion", "async_recursion");
+        future::collapse_future_params(&mut method.sig, Some(&mut method.block));
+        future::inline_async_body(&method.sig, &mut method.block);
+        stream::box_tail(&method.sig, &mut method.block, self.options.no_std());
+        visit_mut::visit_impl_item_method_mut(self, method);
+    }
+
+    fn visit_trait_item_method_mut(&mut self, method: &mut TraitItemMethod) {
+        attr::remove_attribute_macro(&mut method.attrs, "async_recursion", "async_recursion");
+        future::collapse_future_params(&mut method.sig, method.default.as_mut());
+        if let Some(block) = &mut method.default {
+            future::inline_async_body(&method.sig, block);
+            stream::box_tail(&method.sig, block, self.options.no_std());
+        }
+        visit_mut::visit_trait_item_method_mut(self, method);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        if macros::is_macro_rules(mac) {
+            if self.options.macro_rules {
+                mac.tokens = macros::strip_async_tokens(mac.tokens.clone());
+            }
+        } else if macros::is_convertible(mac, &self.options) && !macros::visit_macro_args(self, mac)
+        {
+            mac.tokens = macros::strip_async_tokens(mac.tokens.clone());
+        }
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        // `impl Future<Output = T>`, `Pin<Box<dyn Future<Output = T>>>` and `BoxFuture<T>` to `T`
+        while let Some(output) = future::future_output(ty) {
+            *ty = output;
+        }
+        // `BoxStream<T>` and `Pin<Box<dyn Stream<Item = T>>>` to `Box<dyn Iterator<Item = T>>`
+        stream::convert_type(ty, self.options.no_std());
+        visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_trait_bound_mut(&mut self, bound: &mut TraitBound) {
+        // `AsyncFn(A) -> T` to `Fn(A) -> T`
+        future::convert_async_fn_bound(bound);
+        stream::convert_bound(bound);
+        visit_mut::visit_trait_bound_mut(self, bound);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        // macros in statement position are parsed as items, while the ones
+        // lowered here are expressions
+        if let Stmt::Item(Item::Macro(item)) = stmt {
+            if item.ident.is_none()
+                && (stream::is_stream_macro(&item.mac)
+                    || combinator::is_combinator_macro(&item.mac))
+            {
+                let expr = Expr::Macro(ExprMacro {
+                    attrs: item.attrs.clone(),
+                    mac: item.mac.clone(),
+                });
+                *stmt = match item.semi_token {
+                    Some(semi) => Stmt::Semi(expr, semi),
+                    None => Stmt::Expr(expr),
+                };
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+
     fn visit_expr_mut(&mut self, node: &mut Expr) {
+        // `Box::pin(async { .. })` to `async { .. }`
+        future::unbox_async_block(node);
+        // `Box::pin(fut).await` to `fut.await`, mostly for recursion
+        if let Expr::Await(expr) = node {
+            while let Some(inner) = future::unbox_future(&expr.base) {
+                *expr.base = inner.clone();
+            }
+        }
+        // `fut.instrument(span).await` to `span.in_scope(|| fut)`
+        instrument::convert_expr(node);
+        // `join!`, `join_all` and friends to sequential code
+        combinator::convert_expr(
+            node,
+            self.options.join,
+            &self.future_imports,
+            self.options.no_std(),
+        );
+        // stream constructors and combinators to iterators
+        stream::convert_expr(node, self.options.no_std());
+        // closures returning an async block have to be lowered before the block itself
+        if let Expr::Closure(closure) = node {
+            lower::lower_async_closure(closure);
+        }
+
         // Delegate to the default impl to visit nested expressions.
         visit_mut::visit_expr_mut(self, node);
 
         match node {
             Expr::Await(expr) => *node = (*expr.base).clone(),
 
-            Expr::Async(expr) => {
-                let inner = &expr.block;
-                let sync_expr = if inner.stmts.len() == 1 {
-                    // remove useless braces when there is only one statement
-                    let stmt = &inner.stmts.get(0).unwrap();
-                    // convert statement to Expr
-                    parse_quote!(#stmt)
-                } else {
-                    Expr::Block(ExprBlock {
-                        attrs: expr.attrs.clone(),
-                        block: inner.clone(),
-                        label: None,
-                    })
-                };
-                *node = sync_expr;
-            }
+            Expr::Async(expr) => *node = lower::lower_async_block(expr),
             _ => {}
         }
     }
@@ -0,0 +1,110 @@
+use syn::{parse_quote, Block, Expr};
+
+use super::thread::{self, AwaitBindings};
+use super::Preset;
+
+/// `async_std` to the `std` modules it mirrors, with tasks to threads
+pub const ASYNC_STD: Preset = Preset {
+    name: "async-std",
+    replace: &[
+        ("async_std::fs", "std::fs"),
+        ("async_std::io", "std::io"),
+        ("async_std::io::ReadExt", "std::io::Read"),
+        ("async_std::io::WriteExt", "std::io::Write"),
+        ("async_std::io::BufReadExt", "std::io::BufRead"),
+        ("async_std::io::SeekExt", "std::io::Seek"),
+        ("async_std::net", "std::net"),
+        ("async_std::os", "std::os"),
+        ("async_std::path", "std::path"),
+        ("async_std::prelude", "std::io::prelude"),
+        ("async_std::sync", "std::sync"),
+        ("async_std::task", "std::thread"),
+        ("futures::io::AsyncRead", "std::io::Read"),
+        ("futures::io::AsyncReadExt", "std::io::Read"),
+        ("futures::io::AsyncWrite", "std::io::Write"),
+        ("futures::io::AsyncWriteExt", "std::io::Write"),
+        ("futures::io::AsyncBufRead", "std::io::BufRead"),
+        ("futures::io::AsyncBufReadExt", "std::io::BufRead"),
+        ("futures::io::AsyncSeek", "std::io::Seek"),
+        ("futures::io::AsyncSeekExt", "std::io::Seek"),
+        ("futures::AsyncRead", "std::io::Read"),
+        ("futures::AsyncReadExt", "std::io::Read"),
+        ("futures::AsyncWrite", "std::io::Write"),
+        ("futures::AsyncWriteExt", "std::io::Write"),
+        ("futures::AsyncBufRead", "std::io::BufRead"),
+        ("futures::AsyncBufReadExt", "std::io::BufRead"),
+        ("futures::AsyncSeek", "std::io::Seek"),
+        ("futures::AsyncSeekExt", "std::io::Seek"),
+        ("futures_io::AsyncRead", "std::io::Read"),
+        ("futures_io::AsyncWrite", "std::io::Write"),
+        ("futures_io::AsyncBufRead", "std::io::BufRead"),
+        ("futures_io::AsyncSeek", "std::io::Seek"),
+    ],
+    unsupported: &[
+        (
+            "async_std::task::spawn_local",
+            "call the function directly, or use `std::thread::spawn`",
+        ),
+        (
+            "async_std::task::task_local",
+            "use `std::thread_local!` instead",
+        ),
+        (
+            "async_std::io::timeout",
+            "use a timeout of the blocking call, like `set_read_timeout`",
+        ),
+        (
+            "async_std::future::timeout",
+            "use a timeout of the blocking call, like `set_read_timeout`",
+        ),
+        ("async_std::channel", "use a `std::sync::mpsc` channel"),
+    ],
+    convert_expr,
+    convert_block,
+    convert_fn: |sig, block| thread::convert_sync(sig, block, None),
+    check_fn: |_, block| thread::check_handles(block),
+    no_std: false,
+};
+
+/// `handle.await` to the output of the thread, resuming its panic if any like
+/// awaiting the task does
+fn join(handle: &Expr) -> Expr {
+    parse_quote!(#handle
+        .join()
+        .unwrap_or_else(|panic| ::std::panic::resume_unwind(panic)))
+}
+
+fn convert_expr(node: &mut Expr) -> syn::Result<()> {
+    let call = match node {
+        Expr::Await(expr) if thread::is_spawn(&expr.base) => {
+            *node = join(&expr.base);
+            return Ok(());
+        }
+        Expr::Call(call) if call.args.len() == 1 => call,
+        _ => return Ok(()),
+    };
+    if let Some(spawned) = thread::spawn_thread(call) {
+        *node = spawned;
+        return Ok(());
+    }
+    let name = match thread::called_fn(call) {
+        Some(name) => name.to_string(),
+        None => return Ok(()),
+    };
+    let arg = &call.args[0];
+    match name.as_str() {
+        // the future is already run once converted
+        "block_on" => *node = arg.clone(),
+        "spawn_blocking" => *node = parse_quote!((#arg)()),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Awaiting a `JoinHandle` bound with `let handle = task::spawn(..)` joins
+/// the thread.
+fn convert_block(block: &mut Block) {
+    let mut bindings = AwaitBindings::default();
+    bindings.bind_handles(block, join);
+    bindings.convert(block);
+}
@@ -0,0 +1,76 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_quote, Expr};
+
+use super::{method_call, Preset};
+
+/// `embedded_hal_async` traits to the `embedded_hal` ones, staying `no_std`
+pub const EMBEDDED_HAL: Preset = Preset {
+    name: "embedded-hal",
+    replace: &[
+        ("embedded_hal_async", "embedded_hal"),
+        (
+            "embedded_hal_async::digital::Wait",
+            "embedded_hal::digital::InputPin",
+        ),
+    ],
+    unsupported: &[],
+    convert_expr,
+    convert_block: |_| {},
+    convert_fn: |_, _| {},
+    check_fn: |_, _| {},
+    no_std: true,
+};
+
+/// `Ok(())` once `pin.is_high()` or `pin.is_low()` holds, polling the pin
+fn wait_until(pin: &Expr, state: TokenStream2) -> Expr {
+    parse_quote!(loop {
+        match #pin.#state() {
+            ::core::result::Result::Ok(true) => break ::core::result::Result::Ok(()),
+            ::core::result::Result::Ok(false) => {}
+            ::core::result::Result::Err(error) => break ::core::result::Result::Err(error),
+        }
+    })
+}
+
+/// `Ok(())` once both waits are over, in order
+fn wait_both(first: Expr, second: Expr) -> Expr {
+    parse_quote!(match #first {
+        ::core::result::Result::Ok(()) => #second,
+        ::core::result::Result::Err(error) => ::core::result::Result::Err(error),
+    })
+}
+
+/// The methods of `Wait` polling an `InputPin` instead. Edges are detected
+/// between two reads, so one shorter than the polling period may be missed.
+fn convert_expr(node: &mut Expr) -> syn::Result<()> {
+    let methods = [
+        "wait_for_high",
+        "wait_for_low",
+        "wait_for_rising_edge",
+        "wait_for_falling_edge",
+        "wait_for_any_edge",
+    ];
+    let call = match method_call(node, &methods, 0) {
+        Some(call) => call,
+        None => return Ok(()),
+    };
+    let pin = &*call.receiver;
+    let high = || wait_until(pin, quote!(is_high));
+    let low = || wait_until(pin, quote!(is_low));
+    *node = match call.method.to_string().as_str() {
+        "wait_for_high" => high(),
+        "wait_for_low" => low(),
+        "wait_for_rising_edge" => wait_both(low(), high()),
+        "wait_for_falling_edge" => wait_both(high(), low()),
+        _ => {
+            let (high, low) = (high(), low());
+            parse_quote!(match #pin.is_high() {
+                ::core::result::Result::Ok(true) => #low,
+                ::core::result::Result::Ok(false) => #high,
+                ::core::result::Result::Err(error) => ::core::result::Result::Err(error),
+            })
+        }
+    };
+    Ok(())
+}
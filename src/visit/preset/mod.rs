@@ -0,0 +1,108 @@
+use quote::ToTokens;
+use syn::{Block, Error, Expr, ExprMethodCall, Signature};
+
+mod async_std;
+mod embedded_hal;
+mod postgres;
+mod reqwest;
+mod thread;
+mod tokio;
+mod tungstenite;
+
+/// Conversions of a well known async crate into its blocking counterpart,
+/// enabled with `#[amphi(preset = "name")]`.
+pub(crate) struct Preset {
+    /// name given as `preset = "name"`
+    pub name: &'static str,
+    /// path prefixes and their blocking counterparts, like `replace(..)`
+    /// entries
+    pub replace: &'static [(&'static str, &'static str)],
+    /// path prefixes with no blocking counterpart, and a hint on what to do
+    /// instead
+    pub unsupported: &'static [(&'static str, &'static str)],
+    /// rewrite of expressions not covered by paths, like method calls
+    pub convert_expr: fn(&mut Expr) -> syn::Result<()>,
+    /// rewrite of statements depending on the bindings of a block, after its
+    /// expressions are converted
+    pub convert_block: fn(&mut Block),
+    /// rewrite of a function body depending on the parameters, after the body
+    /// is converted
+    pub convert_fn: fn(&Signature, &mut Block),
+    /// replacement of code the preset cannot convert by a compile error, in a
+    /// function body before it is converted, depending on the parameters
+    pub check_fn: fn(&Signature, &mut Block),
+    /// whether the blocking version stays `no_std`, so that conversions
+    /// needing threads or allocation are errors
+    pub no_std: bool,
+}
+
+const PRESETS: &[&Preset] = &[
+    &tokio::TOKIO,
+    &async_std::ASYNC_STD,
+    &reqwest::REQWEST,
+    &embedded_hal::EMBEDDED_HAL,
+    &tungstenite::TUNGSTENITE,
+    &postgres::POSTGRES,
+];
+
+/// error message of an unknown preset
+pub const UNKNOWN_PRESET: &str = "Unknown preset, only `tokio`, `async-std`, `reqwest`, \
+     `embedded-hal`, `tungstenite` and `postgres` are available";
+
+/// The preset of the given name.
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().copied().find(|preset| preset.name == name)
+}
+
+/// An error pointing at code whose conversion, described by `what`, needs
+/// `std` while `preset` keeps the blocking version `no_std`.
+pub fn needs_std(tokens: impl ToTokens, what: &str, preset: &str) -> Error {
+    Error::new_spanned(
+        tokens,
+        format!(
+            "{} needs `std` in the blocking version, which the `{}` preset keeps `no_std`",
+            what, preset
+        ),
+    )
+}
+
+/// An error pointing at a method call with no blocking equivalent.
+fn unsupported_method(call: &ExprMethodCall, hint: &str) -> Error {
+    Error::new(
+        call.method.span(),
+        format!("`{}` has no blocking equivalent: {}", call.method, hint),
+    )
+}
+
+/// The method call of an expression, if its name is one of `methods` and it
+/// takes `args` arguments.
+fn method_call<'a>(expr: &'a Expr, methods: &[&str], args: usize) -> Option<&'a ExprMethodCall> {
+    match expr {
+        Expr::MethodCall(call)
+            if call.args.len() == args && methods.iter().any(|method| call.method == method) =>
+        {
+            Some(call)
+        }
+        _ => None,
+    }
+}
+
+/// The expression producing a value, without the `?`, `.await`, `.unwrap()`
+/// and the like around it.
+fn produced_by(mut expr: &Expr) -> &Expr {
+    loop {
+        expr = match expr {
+            Expr::Try(inner) => &inner.expr,
+            Expr::Await(inner) => &inner.base,
+            Expr::Paren(inner) => &inner.expr,
+            Expr::MethodCall(call)
+                if ["unwrap", "expect", "map_err"]
+                    .iter()
+                    .any(|method| call.method == method) =>
+            {
+                &call.receiver
+            }
+            _ => return expr,
+        };
+    }
+}
@@ -0,0 +1,161 @@
+use syn::{
+    visit::{self, Visit},
+    Block, Expr, ExprMethodCall, ExprPath, Ident, Local, Pat, Stmt,
+};
+
+use super::thread::{called_fn, pat_ident};
+use super::{produced_by, Preset};
+
+/// `tokio_postgres` to `postgres`
+pub const POSTGRES: Preset = Preset {
+    name: "postgres",
+    replace: &[
+        ("tokio_postgres", "postgres"),
+        ("tokio_postgres::connect", "postgres::Client::connect"),
+    ],
+    unsupported: &[
+        (
+            "tokio_postgres::Connection",
+            "the blocking `postgres::Client` drives its own connection",
+        ),
+        (
+            "tokio_postgres::AsyncMessage",
+            "use `postgres::Client::notifications`",
+        ),
+    ],
+    convert_expr: |_| Ok(()),
+    convert_block,
+    convert_fn: |_, _| {},
+    check_fn: |_, _| {},
+    no_std: false,
+};
+
+/// whether an expression connects to the database, like
+/// `tokio_postgres::connect(params, NoTls).await?` or `config.connect(NoTls)`
+fn is_connect(expr: &Expr) -> bool {
+    match produced_by(expr) {
+        Expr::Call(call) => called_fn(call).is_some_and(|name| name == "connect"),
+        Expr::MethodCall(call) => call.method == "connect" && call.args.len() == 1,
+        _ => false,
+    }
+}
+
+/// Turn `let (client, connection) = connect(..)` into `let mut client =
+/// connect(..)`, and return the name of the connection.
+fn split_connection(local: &mut Local) -> Option<Ident> {
+    if !local
+        .init
+        .as_ref()
+        .is_some_and(|(_, init)| is_connect(init))
+    {
+        return None;
+    }
+    let tuple = match &mut local.pat {
+        Pat::Tuple(tuple) if tuple.elems.len() == 2 => tuple,
+        _ => return None,
+    };
+    let connection = pat_ident(&tuple.elems[1]).cloned();
+    let mut client = tuple.elems[0].clone();
+    // the methods of the blocking client take `&mut self`
+    if let Pat::Ident(client) = &mut client {
+        client.mutability = Some(Default::default());
+    }
+    local.pat = client;
+    connection
+}
+
+/// whether a path named `ident` appears in a statement
+struct Mentions<'a> {
+    ident: &'a Ident,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for Mentions<'_> {
+    fn visit_expr_path(&mut self, path: &'ast ExprPath) {
+        self.found |= path.path.is_ident(self.ident);
+        visit::visit_expr_path(self, path);
+    }
+}
+
+/// whether `ident` is the receiver of a method borrowing it, unlike `commit`
+/// and `rollback` of a transaction
+struct Queried<'a> {
+    ident: &'a Ident,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for Queried<'_> {
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        let receiver =
+            matches!(&*call.receiver, Expr::Path(path) if path.path.is_ident(self.ident));
+        self.found |= receiver && call.method != "commit" && call.method != "rollback";
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// Make `let transaction = client.transaction().await?` mutable if it is
+/// queried, since the methods of a blocking transaction take `&mut self`.
+fn mut_transaction(local: &mut Local, stmts: &[Stmt]) {
+    let is_transaction = local.init.as_ref().is_some_and(|(_, init)| {
+        matches!(produced_by(init), Expr::MethodCall(call)
+            if call.method == "transaction" || call.method == "start")
+    });
+    let pat = match &mut local.pat {
+        Pat::Ident(pat) if is_transaction => pat,
+        _ => return,
+    };
+    let mut queried = Queried {
+        ident: &pat.ident,
+        found: false,
+    };
+    for stmt in stmts {
+        queried.visit_stmt(stmt);
+    }
+    if queried.found {
+        pat.mutability = Some(Default::default());
+    }
+}
+
+/// whether a statement spawns a task driving the connection, like
+/// `tokio::spawn(connection)` or `let _ = tokio::spawn(async move { .. })`
+fn drives(stmt: &Stmt, connection: &Ident) -> bool {
+    let expr = match stmt {
+        Stmt::Expr(expr) | Stmt::Semi(expr, _) => expr,
+        Stmt::Local(Local {
+            pat: Pat::Wild(_),
+            init: Some((_, init)),
+            ..
+        }) => init,
+        _ => return false,
+    };
+    let call = match expr {
+        Expr::Call(call) if called_fn(call).is_some_and(|name| name == "spawn") => call,
+        _ => return false,
+    };
+    let mut mentions = Mentions {
+        ident: connection,
+        found: false,
+    };
+    for arg in &call.args {
+        mentions.visit_expr(arg);
+    }
+    mentions.found
+}
+
+/// A blocking client owns its connection, so the connection is not returned
+/// by `connect` nor driven by a task. A blocking transaction is mutable.
+fn convert_block(block: &mut Block) {
+    let mut connections = vec![];
+    for i in 0..block.stmts.len() {
+        let (stmt, rest) = block.stmts[i..].split_first_mut().unwrap();
+        if let Stmt::Local(local) = stmt {
+            connections.extend(split_connection(local));
+            mut_transaction(local, rest);
+        }
+    }
+    block.stmts.retain(|stmt| {
+        !connections
+            .iter()
+            .any(|connection| drives(stmt, connection))
+    });
+}
@@ -0,0 +1,53 @@
+use syn::Expr;
+
+use super::{method_call, unsupported_method, Preset};
+
+/// the async `reqwest` client to `reqwest::blocking`
+pub const REQWEST: Preset = Preset {
+    name: "reqwest",
+    replace: &[
+        ("reqwest::Client", "reqwest::blocking::Client"),
+        ("reqwest::ClientBuilder", "reqwest::blocking::ClientBuilder"),
+        (
+            "reqwest::RequestBuilder",
+            "reqwest::blocking::RequestBuilder",
+        ),
+        ("reqwest::Response", "reqwest::blocking::Response"),
+        ("reqwest::Body", "reqwest::blocking::Body"),
+        ("reqwest::multipart", "reqwest::blocking::multipart"),
+        ("reqwest::get", "reqwest::blocking::get"),
+    ],
+    unsupported: &[
+        (
+            "reqwest::Body::wrap_stream",
+            "give a `std::io::Read` to `reqwest::blocking::Body::new`",
+        ),
+        (
+            "reqwest::Upgraded",
+            "a blocking client cannot upgrade connections",
+        ),
+    ],
+    convert_expr,
+    convert_block: |_| {},
+    convert_fn: |_, _| {},
+    check_fn: |_, _| {},
+    no_std: false,
+};
+
+/// Streaming the body has no blocking counterpart, the blocking `Response`
+/// is read whole or as `std::io::Read` instead.
+fn convert_expr(node: &mut Expr) -> syn::Result<()> {
+    if let Some(call) = method_call(node, &["bytes_stream", "chunk"], 0) {
+        return Err(unsupported_method(
+            call,
+            "read the response with `std::io::Read`, or whole with `bytes` or `text`",
+        ));
+    }
+    if let Some(call) = method_call(node, &["upgrade"], 0) {
+        return Err(unsupported_method(
+            call,
+            "a blocking client cannot upgrade connections",
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,424 @@
+use std::collections::{HashMap, HashSet};
+
+use syn::{
+    parse_quote,
+    visit::{self, Visit},
+    visit_mut::{self, VisitMut},
+    Block, Error, Expr, ExprCall, FnArg, GenericArgument, Ident, Item, Local, Pat, PathArguments,
+    Signature, Stmt, Type,
+};
+
+use super::produced_by;
+use crate::visit::error_expr;
+
+/// The name of the function called, like `spawn` for `std::thread::spawn(f)`.
+pub fn called_fn(call: &ExprCall) -> Option<&Ident> {
+    match &*call.func {
+        Expr::Path(func) if func.qself.is_none() => Some(&func.path.segments.last()?.ident),
+        _ => None,
+    }
+}
+
+/// whether an expression spawns a thread, once converted, possibly as the
+/// value of a block
+pub fn is_spawn(expr: &Expr) -> bool {
+    match expr {
+        Expr::Block(block) => match block.block.stmts.last() {
+            Some(Stmt::Expr(expr)) => is_spawn(expr),
+            _ => false,
+        },
+        Expr::Call(call) => {
+            called_fn(call).is_some_and(|name| name == "spawn")
+                && matches!(call.args.first(), Some(Expr::Closure(_)))
+        }
+        _ => false,
+    }
+}
+
+/// A task spawned on a thread instead:
+///
+/// - `spawn(async move { .. })` to `spawn(move || { .. })`
+/// - `spawn(fetch(url))` to `spawn(move || fetch(url))`
+pub fn spawn_thread(call: &ExprCall) -> Option<Expr> {
+    if call.args.len() != 1 || called_fn(call).is_none_or(|name| name != "spawn") {
+        return None;
+    }
+    let func = &call.func;
+    match &call.args[0] {
+        Expr::Async(block) => {
+            let capture = &block.capture;
+            let body = &block.block;
+            Some(parse_quote!(#func(#capture || #body)))
+        }
+        arg @ (Expr::Call(_) | Expr::MethodCall(_)) => Some(parse_quote!(#func(move || #arg))),
+        _ => None,
+    }
+}
+
+/// whether an expression spawns a task that `spawn_thread` converts, before
+/// it is converted, possibly as the value of a block
+fn is_task_spawn(expr: &Expr) -> bool {
+    match expr {
+        Expr::Block(block) => match block.block.stmts.last() {
+            Some(Stmt::Expr(expr)) => is_task_spawn(expr),
+            _ => false,
+        },
+        Expr::Call(call) => is_task_spawn_call(call),
+        _ => false,
+    }
+}
+
+/// whether a call spawns a task that `spawn_thread` converts
+fn is_task_spawn_call(call: &ExprCall) -> bool {
+    call.args.len() == 1
+        && called_fn(call).is_some_and(|name| name == "spawn")
+        && matches!(
+            call.args[0],
+            Expr::Async(_) | Expr::Call(_) | Expr::MethodCall(_)
+        )
+}
+
+/// Count the uses of a binding, and how many of them are awaited as is.
+struct HandleUses<'a> {
+    ident: &'a Ident,
+    used: usize,
+    awaited: usize,
+}
+
+impl<'ast> Visit<'ast> for HandleUses<'_> {
+    fn visit_expr(&mut self, node: &'ast Expr) {
+        match node {
+            Expr::Path(path) if path.path.is_ident(self.ident) => self.used += 1,
+            Expr::Await(expr) => match &*expr.base {
+                Expr::Path(path) if path.path.is_ident(self.ident) => {
+                    self.used += 1;
+                    self.awaited += 1;
+                }
+                _ => visit::visit_expr(self, node),
+            },
+            _ => visit::visit_expr(self, node),
+        }
+    }
+}
+
+/// whether the handle bound by `pat` is dropped, or only awaited as is in the
+/// statements following its binding
+fn handle_joined(pat: &Pat, following: &[Stmt]) -> bool {
+    let ident = match pat {
+        Pat::Wild(_) => return true,
+        pat => match pat_ident(pat) {
+            Some(ident) => ident,
+            None => return false,
+        },
+    };
+    let mut uses = HandleUses {
+        ident,
+        used: 0,
+        awaited: 0,
+    };
+    for stmt in following {
+        uses.visit_stmt(stmt);
+    }
+    uses.used == uses.awaited
+}
+
+/// Replace the spawned tasks whose handle is not joined by a compile error.
+struct UnjoinedHandles;
+
+impl UnjoinedHandles {
+    /// Visit the statements of a block, whose value is a handle to join if
+    /// `joined`.
+    fn visit_stmts(&mut self, block: &mut Block, joined: bool) {
+        let len = block.stmts.len();
+        for i in 0..len {
+            let (stmt, following) = block.stmts[i..].split_first_mut().unwrap();
+            match stmt {
+                // dropping a handle detaches the thread
+                Stmt::Semi(expr, _) if is_task_spawn(expr) => self.visit_spawned(expr),
+                Stmt::Expr(expr) if joined && i + 1 == len => self.visit_spawned(expr),
+                Stmt::Local(local) => match &mut local.init {
+                    Some((_, init))
+                        if is_task_spawn(init) && handle_joined(&local.pat, following) =>
+                    {
+                        self.visit_spawned(init)
+                    }
+                    _ => self.visit_local_mut(local),
+                },
+                stmt => self.visit_stmt_mut(stmt),
+            }
+        }
+    }
+
+    /// Visit the task spawned by an expression, without the spawn itself.
+    fn visit_spawned(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Block(block) => self.visit_stmts(&mut block.block, true),
+            Expr::Call(call) => call
+                .args
+                .iter_mut()
+                .for_each(|arg| self.visit_expr_mut(arg)),
+            expr => self.visit_expr_mut(expr),
+        }
+    }
+}
+
+impl VisitMut for UnjoinedHandles {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        self.visit_stmts(block, false);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        match node {
+            Expr::Await(expr) if is_task_spawn(&expr.base) => self.visit_spawned(&mut expr.base),
+            Expr::Call(call) if is_task_spawn_call(call) => {
+                let error = Error::new_spanned(
+                    &call.func,
+                    "the handle of a spawned task is only joined in the blocking version \
+                     when the task is awaited right away, or bound by `let` and only awaited",
+                );
+                *node = error_expr(&error);
+            }
+            node => visit_mut::visit_expr_mut(self, node),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _node: &mut Item) {}
+}
+
+/// Check that the handles of spawned tasks are joined where they are awaited.
+///
+/// Awaiting a handle only joins the thread when the task is awaited right
+/// away, or when its `let` binding is only ever awaited, as the type of other
+/// awaited expressions is unknown. Other spawned tasks whose handle is kept
+/// are errors, while dropping a handle detaches the thread.
+pub fn check_handles(block: &mut Block) {
+    UnjoinedHandles.visit_block_mut(block);
+}
+
+/// the identifier bound by a pattern, like `rx` or `mut rx: Receiver<T>`
+pub fn pat_ident(pat: &Pat) -> Option<&Ident> {
+    match pat {
+        Pat::Ident(pat) => Some(&pat.ident),
+        Pat::Type(pat) => pat_ident(&pat.pat),
+        _ => None,
+    }
+}
+
+/// Conversions of `.await` on the bindings of a block, like the `JoinHandle`
+/// of `let handle = spawn(..)`
+#[derive(Default)]
+pub struct AwaitBindings(HashMap<Ident, fn(&Expr) -> Expr>);
+
+impl AwaitBindings {
+    /// Convert `ident.await` with `convert` given `ident`.
+    pub fn bind(&mut self, ident: &Ident, convert: fn(&Expr) -> Expr) {
+        self.0.insert(ident.clone(), convert);
+    }
+
+    /// Convert awaiting the handles of threads spawned by the `let` statements
+    /// of a block with `join`.
+    pub fn bind_handles(&mut self, block: &Block, join: fn(&Expr) -> Expr) {
+        for stmt in &block.stmts {
+            if let Stmt::Local(local) = stmt {
+                let spawned = local.init.as_ref().is_some_and(|(_, init)| is_spawn(init));
+                if let (true, Some(ident)) = (spawned, pat_ident(&local.pat)) {
+                    self.bind(ident, join);
+                }
+            }
+        }
+    }
+
+    pub fn convert(mut self, block: &mut Block) {
+        if !self.0.is_empty() {
+            self.visit_block_mut(block);
+        }
+    }
+}
+
+impl VisitMut for AwaitBindings {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_mut::visit_expr_mut(self, node);
+        let base = match node {
+            Expr::Await(expr) => &expr.base,
+            _ => return,
+        };
+        let convert = match &**base {
+            Expr::Path(path) => match path.path.get_ident().and_then(|ident| self.0.get(ident)) {
+                Some(convert) => convert,
+                None => return,
+            },
+            _ => return,
+        };
+        *node = convert(base);
+    }
+}
+
+/// whether a type is an async lock, possibly in an `Arc` or behind a reference
+fn is_lock_type(ty: &Type) -> bool {
+    let segment = match ty {
+        Type::Reference(ty) => return is_lock_type(&ty.elem),
+        Type::Path(ty) => match ty.path.segments.last() {
+            Some(segment) => segment,
+            None => return false,
+        },
+        _ => return false,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if segment.ident == "Arc" => {
+            matches!(args.args.first(), Some(GenericArgument::Type(ty)) if is_lock_type(ty))
+        }
+        _ => segment.ident == "Mutex" || segment.ident == "RwLock",
+    }
+}
+
+/// whether a type is a channel receiver, possibly behind a reference
+fn is_receiver_type(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(ty) => is_receiver_type(&ty.elem),
+        Type::Path(ty) => ty.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Receiver" || segment.ident == "UnboundedReceiver"
+        }),
+        _ => false,
+    }
+}
+
+/// Async locks and channel receivers bound in a function, whose awaited
+/// methods have blocking counterparts.
+struct SyncBindings {
+    locks: HashSet<Ident>,
+    receivers: HashSet<Ident>,
+    recv: Option<fn(&Expr) -> Expr>,
+}
+
+impl SyncBindings {
+    /// whether an expression makes a lock, like `Arc::new(Mutex::new(0))`, or
+    /// shares a bound one, like `counter.clone()` or `Arc::clone(&counter)`
+    fn is_lock(&self, expr: &Expr) -> bool {
+        match produced_by(expr) {
+            Expr::Call(call) if call.args.len() == 1 => {
+                let mut segments = match &*call.func {
+                    Expr::Path(func) if func.qself.is_none() => func.path.segments.iter().rev(),
+                    _ => return false,
+                };
+                let (func, ty) = match (segments.next(), segments.next()) {
+                    (Some(func), Some(ty)) => (&func.ident, &ty.ident),
+                    _ => return false,
+                };
+                let arg = &call.args[0];
+                match func.to_string().as_str() {
+                    "new" => ty == "Mutex" || ty == "RwLock" || ty == "Arc" && self.is_lock(arg),
+                    "clone" => ty == "Arc" && self.is_lock(arg),
+                    _ => false,
+                }
+            }
+            Expr::MethodCall(call) if call.method == "clone" && call.args.is_empty() => {
+                self.is_lock(&call.receiver)
+            }
+            Expr::Reference(expr) => self.is_lock(&expr.expr),
+            expr => Self::is_bound(expr, &self.locks),
+        }
+    }
+
+    fn is_bound(expr: &Expr, bindings: &HashSet<Ident>) -> bool {
+        match expr {
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| bindings.contains(ident)),
+            _ => false,
+        }
+    }
+
+    /// Bind the parameters of type `Mutex<T>`, `RwLock<T>` and `Receiver<T>`,
+    /// in an `Arc` or behind a reference.
+    fn bind_params(&mut self, sig: &Signature) {
+        for input in &sig.inputs {
+            let input = match input {
+                FnArg::Typed(input) => input,
+                FnArg::Receiver(_) => continue,
+            };
+            let ident = pat_ident(&input.pat).cloned();
+            if is_lock_type(&input.ty) {
+                self.locks.extend(ident);
+            } else if is_receiver_type(&input.ty) {
+                self.receivers.extend(ident);
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for SyncBindings {
+    fn visit_local(&mut self, local: &'ast Local) {
+        visit::visit_local(self, local);
+        let init = match &local.init {
+            Some((_, init)) => &**init,
+            None => return,
+        };
+        let (pat, ty) = match &local.pat {
+            Pat::Type(pat) => (&*pat.pat, Some(&*pat.ty)),
+            pat => (pat, None),
+        };
+        if self.is_lock(init) || ty.is_some_and(is_lock_type) {
+            self.locks.extend(pat_ident(pat).cloned());
+        }
+        // `let (tx, rx) = channel()`
+        let is_channel = match produced_by(init) {
+            Expr::Call(call) => {
+                called_fn(call).is_some_and(|name| name == "channel" || name == "sync_channel")
+            }
+            _ => false,
+        };
+        if let (true, Pat::Tuple(tuple)) = (is_channel, pat) {
+            self.receivers
+                .extend(tuple.elems.iter().nth(1).and_then(pat_ident).cloned());
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast Item) {}
+}
+
+impl VisitMut for SyncBindings {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_mut::visit_expr_mut(self, node);
+        let base = match node {
+            Expr::Await(expr) => &expr.base,
+            _ => return,
+        };
+        let call = match &**base {
+            Expr::MethodCall(call) if call.args.is_empty() => call,
+            _ => return,
+        };
+        *node = match (call.method.to_string().as_str(), self.recv) {
+            ("lock" | "read" | "write", _) if Self::is_bound(&call.receiver, &self.locks) => {
+                parse_quote!(#base.unwrap())
+            }
+            ("recv", Some(recv)) if Self::is_bound(&call.receiver, &self.receivers) => recv(base),
+            _ => return,
+        };
+    }
+
+    fn visit_item_mut(&mut self, _node: &mut Item) {}
+}
+
+/// Convert the awaited methods of the async locks and channel receivers of a
+/// function, bound by its parameters or `let` statements:
+///
+/// - `mutex.lock().await` to `mutex.lock().unwrap()`, and the same for `read`
+///   and `write` of a `RwLock`, since async locks are not poisoned
+/// - `rx.recv().await` with `recv`, if given
+///
+/// Locks are bound to `Mutex::new(..)` or `RwLock::new(..)`, possibly in an
+/// `Arc`, or to a clone of one. Receivers are bound by `let (tx, rx) =
+/// channel()`. Methods of the same name on other values are kept.
+pub fn convert_sync(sig: &Signature, block: &mut Block, recv: Option<fn(&Expr) -> Expr>) {
+    let mut bindings = SyncBindings {
+        locks: HashSet::new(),
+        receivers: HashSet::new(),
+        recv,
+    };
+    bindings.bind_params(sig);
+    bindings.visit_block(block);
+    if !bindings.locks.is_empty() || !bindings.receivers.is_empty() {
+        bindings.visit_block_mut(block);
+    }
+}
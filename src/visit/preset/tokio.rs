@@ -0,0 +1,508 @@
+use std::collections::HashSet;
+
+use syn::{
+    parse_quote,
+    visit::{self, Visit},
+    visit_mut::{self, VisitMut},
+    Block, Expr, FnArg, Ident, Item, Local, Pat, Signature, Stmt, Type, TypeParamBound,
+    WherePredicate,
+};
+
+use super::thread::{self, AwaitBindings};
+use super::{method_call, produced_by, unsupported_method, Preset};
+use crate::visit::error_expr;
+
+/// `tokio` I/O, file system, networking, tasks, time and synchronization
+/// primitives to `std`
+pub const TOKIO: Preset = Preset {
+    name: "tokio",
+    replace: &[
+        ("tokio::fs", "std::fs"),
+        ("tokio::fs::try_exists", "std::fs::exists"),
+        ("tokio::io", "std::io"),
+        ("tokio::io::AsyncRead", "std::io::Read"),
+        ("tokio::io::AsyncReadExt", "std::io::Read"),
+        ("tokio::io::AsyncWrite", "std::io::Write"),
+        ("tokio::io::AsyncWriteExt", "std::io::Write"),
+        ("tokio::io::AsyncBufRead", "std::io::BufRead"),
+        ("tokio::io::AsyncBufReadExt", "std::io::BufRead"),
+        ("tokio::io::AsyncSeek", "std::io::Seek"),
+        ("tokio::io::AsyncSeekExt", "std::io::Seek"),
+        ("tokio::io::copy_buf", "std::io::copy"),
+        ("tokio::net::TcpListener", "std::net::TcpListener"),
+        ("tokio::net::TcpStream", "std::net::TcpStream"),
+        ("tokio::net::UdpSocket", "std::net::UdpSocket"),
+        ("tokio::net::ToSocketAddrs", "std::net::ToSocketAddrs"),
+        (
+            "tokio::net::UnixDatagram",
+            "std::os::unix::net::UnixDatagram",
+        ),
+        (
+            "tokio::net::UnixListener",
+            "std::os::unix::net::UnixListener",
+        ),
+        ("tokio::net::UnixStream", "std::os::unix::net::UnixStream"),
+        ("tokio::spawn", "std::thread::spawn"),
+        ("tokio::task", "std::thread"),
+        ("tokio::time", "std::time"),
+        ("tokio::time::sleep", "std::thread::sleep"),
+        ("tokio::sync", "std::sync"),
+        (
+            "tokio::sync::mpsc::channel",
+            "std::sync::mpsc::sync_channel",
+        ),
+        ("tokio::sync::mpsc::Sender", "std::sync::mpsc::SyncSender"),
+        (
+            "tokio::sync::mpsc::unbounded_channel",
+            "std::sync::mpsc::channel",
+        ),
+        (
+            "tokio::sync::mpsc::UnboundedSender",
+            "std::sync::mpsc::Sender",
+        ),
+        (
+            "tokio::sync::mpsc::UnboundedReceiver",
+            "std::sync::mpsc::Receiver",
+        ),
+        ("tokio::sync::oneshot", "std::sync::mpsc"),
+    ],
+    unsupported: &[
+        (
+            "tokio::io::split",
+            "use `try_clone` of the std type for a second handle",
+        ),
+        (
+            "tokio::io::ReadHalf",
+            "use `try_clone` of the std type instead",
+        ),
+        (
+            "tokio::io::WriteHalf",
+            "use `try_clone` of the std type instead",
+        ),
+        (
+            "tokio::io::copy_bidirectional",
+            "copy each direction with `std::io::copy` on its own thread",
+        ),
+        ("tokio::io::duplex", "std has no in-memory pipe"),
+        ("tokio::io::DuplexStream", "std has no in-memory pipe"),
+        (
+            "tokio::io::BufStream",
+            "wrap the reader in `BufReader` and the writer in `BufWriter`",
+        ),
+        ("tokio::io::ReadBuf", "read into a `&mut [u8]` instead"),
+        ("tokio::io::Interest", "blocking I/O has no readiness"),
+        ("tokio::io::Ready", "blocking I/O has no readiness"),
+        ("tokio::io::unix", "blocking I/O has no readiness"),
+        (
+            "tokio::net::lookup_host",
+            "use `std::net::ToSocketAddrs::to_socket_addrs`",
+        ),
+        (
+            "tokio::net::TcpSocket",
+            "use `std::net::TcpStream::connect` or `std::net::TcpListener::bind`",
+        ),
+        ("tokio::net::tcp", "use `try_clone` of the std type instead"),
+        (
+            "tokio::net::unix",
+            "use `try_clone` of the std type instead",
+        ),
+        (
+            "tokio::time::timeout",
+            "use a timeout of the blocking call, like `set_read_timeout` or `recv_timeout`",
+        ),
+        (
+            "tokio::time::timeout_at",
+            "use a timeout of the blocking call, like `set_read_timeout` or `recv_timeout`",
+        ),
+        (
+            "tokio::time::Timeout",
+            "use a timeout of the blocking call, like `set_read_timeout` or `recv_timeout`",
+        ),
+        (
+            "tokio::time::interval",
+            "call `std::thread::sleep` between the ticks",
+        ),
+        (
+            "tokio::time::interval_at",
+            "call `std::thread::sleep` between the ticks",
+        ),
+        (
+            "tokio::time::Interval",
+            "call `std::thread::sleep` between the ticks",
+        ),
+        (
+            "tokio::time::MissedTickBehavior",
+            "call `std::thread::sleep` between the ticks",
+        ),
+        (
+            "tokio::sync::broadcast",
+            "use a `std::sync::mpsc` channel for each receiver",
+        ),
+        (
+            "tokio::sync::watch",
+            "share the value behind a `std::sync::RwLock` and a `std::sync::Condvar`",
+        ),
+        ("tokio::sync::Notify", "use a `std::sync::Condvar`"),
+        (
+            "tokio::sync::Semaphore",
+            "use a `std::sync::Mutex` counter and a `std::sync::Condvar`",
+        ),
+        ("tokio::sync::OnceCell", "use `std::sync::OnceLock`"),
+        ("tokio::task::JoinSet", "keep the `JoinHandle`s in a `Vec`"),
+        ("tokio::task::LocalSet", "threads have no local set"),
+        (
+            "tokio::task::spawn_local",
+            "call the function directly, or use `std::thread::spawn`",
+        ),
+    ],
+    convert_expr,
+    convert_block,
+    convert_fn,
+    check_fn,
+    no_std: false,
+};
+
+/// `read_u32` and friends of `AsyncReadExt`, `write_u32` and friends of
+/// `AsyncWriteExt`
+fn is_number_io(method: &str) -> bool {
+    let number = match method
+        .strip_prefix("read_")
+        .or_else(|| method.strip_prefix("write_"))
+    {
+        Some(number) => number.strip_suffix("_le").unwrap_or(number),
+        None => return false,
+    };
+    matches!(
+        number,
+        "u8" | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "i8"
+            | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "f32"
+            | "f64"
+    )
+}
+
+/// Convert tasks, their handles and the awaited methods of synchronization
+/// primitives.
+fn convert_task(node: &mut Expr) -> syn::Result<bool> {
+    if let Expr::Await(expr) = node {
+        let base = &expr.base;
+        if thread::is_spawn(base) {
+            *node = join(base);
+            return Ok(true);
+        }
+        return Ok(false);
+    }
+    if let Some(call) = method_call(node, &["lock_owned", "read_owned", "write_owned"], 0) {
+        return Err(unsupported_method(
+            call,
+            "lock the `Arc` with `lock`, `read` or `write`",
+        ));
+    }
+    let call = match node {
+        Expr::Call(call) if call.args.len() == 1 => call,
+        _ => return Ok(false),
+    };
+    if let Some(spawned) = thread::spawn_thread(call) {
+        *node = spawned;
+        return Ok(true);
+    }
+    let name = match thread::called_fn(call) {
+        Some(name) => name.to_string(),
+        None => return Ok(false),
+    };
+    let arg = &call.args[0];
+    *node = match (name.as_str(), arg) {
+        // the result of the direct call, as if joined
+        ("spawn_blocking", f) => parse_quote!(
+            ::core::result::Result::<
+                _,
+                ::std::boxed::Box<dyn ::core::any::Any + ::core::marker::Send>,
+            >::Ok((#f)())
+        ),
+        ("block_in_place", f) => parse_quote!((#f)()),
+        ("sleep_until", deadline) => parse_quote!(::std::thread::sleep(
+            #deadline.saturating_duration_since(::std::time::Instant::now())
+        )),
+        _ => return Ok(false),
+    };
+    Ok(true)
+}
+
+fn convert_expr(node: &mut Expr) -> syn::Result<()> {
+    if convert_task(node)? {
+        return Ok(());
+    }
+    // `lines.next_line()` and `dir.next_entry()` to `Option<Result<T>>` iterators
+    if let Some(call) = method_call(node, &["next_line", "next_entry"], 0) {
+        let receiver = &call.receiver;
+        *node = parse_quote!(#receiver.next().transpose());
+        return Ok(());
+    }
+    Ok(())
+}
+
+/// tokio I/O types, whose methods are checked
+const IO_TYPES: &[&str] = &[
+    "File",
+    "TcpStream",
+    "TcpListener",
+    "UnixStream",
+    "UnixListener",
+    "BufReader",
+    "BufWriter",
+    "BufStream",
+    "ReadHalf",
+    "WriteHalf",
+    "OwnedReadHalf",
+    "OwnedWriteHalf",
+    "Stdin",
+    "Stdout",
+    "Stderr",
+];
+
+/// tokio I/O traits, whose implementors are checked
+const IO_TRAITS: &[&str] = &[
+    "AsyncRead",
+    "AsyncWrite",
+    "AsyncBufRead",
+    "AsyncReadExt",
+    "AsyncWriteExt",
+    "AsyncBufReadExt",
+];
+
+fn is_io_bound(bound: &TypeParamBound) -> bool {
+    match bound {
+        TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| IO_TRAITS.iter().any(|name| segment.ident == name)),
+        TypeParamBound::Lifetime(_) => false,
+    }
+}
+
+/// The I/O values of a function, bound by its parameters or `let` statements,
+/// whose methods without a blocking equivalent are errors.
+struct IoBindings {
+    /// type parameters bounded by an I/O trait, like `R` in `<R: AsyncRead>`
+    generics: HashSet<Ident>,
+    bindings: HashSet<Ident>,
+}
+
+impl IoBindings {
+    fn new(sig: &Signature) -> Self {
+        let mut generics = HashSet::new();
+        for param in sig.generics.type_params() {
+            if param.bounds.iter().any(is_io_bound) {
+                generics.insert(param.ident.clone());
+            }
+        }
+        let predicates = sig.generics.where_clause.iter().flat_map(|w| &w.predicates);
+        for predicate in predicates {
+            let predicate = match predicate {
+                WherePredicate::Type(predicate) => predicate,
+                _ => continue,
+            };
+            if let (Type::Path(ty), true) = (
+                &predicate.bounded_ty,
+                predicate.bounds.iter().any(is_io_bound),
+            ) {
+                generics.extend(ty.path.get_ident().cloned());
+            }
+        }
+        let mut io = IoBindings {
+            generics,
+            bindings: HashSet::new(),
+        };
+        for input in &sig.inputs {
+            if let FnArg::Typed(input) = input {
+                if io.is_io_type(&input.ty) {
+                    io.bindings.extend(thread::pat_ident(&input.pat).cloned());
+                }
+            }
+        }
+        io
+    }
+
+    /// whether a type is an I/O type, an I/O type parameter or `impl AsyncRead`
+    /// and the like, possibly behind a reference
+    fn is_io_type(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Reference(ty) => self.is_io_type(&ty.elem),
+            Type::ImplTrait(ty) => ty.bounds.iter().any(is_io_bound),
+            Type::Path(ty) => {
+                ty.path
+                    .get_ident()
+                    .is_some_and(|ident| self.generics.contains(ident))
+                    || ty
+                        .path
+                        .segments
+                        .last()
+                        .is_some_and(|segment| IO_TYPES.iter().any(|name| segment.ident == name))
+            }
+            _ => false,
+        }
+    }
+
+    fn is_bound(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| self.bindings.contains(ident)),
+            Expr::Reference(expr) => self.is_bound(&expr.expr),
+            _ => false,
+        }
+    }
+
+    /// whether an expression opens I/O, like `TcpStream::connect(addr)`,
+    /// `BufReader::new(file)` or `tokio::io::split(stream)`, or accepts a
+    /// connection of a bound listener
+    fn is_io(&self, expr: &Expr) -> bool {
+        match produced_by(expr) {
+            Expr::Call(call) => match &*call.func {
+                Expr::Path(func) if func.qself.is_none() => {
+                    let mut segments = func.path.segments.iter().rev();
+                    let opened = segments.next().is_some_and(|func| {
+                        ["split", "stdin", "stdout", "stderr"]
+                            .iter()
+                            .any(|name| func.ident == name)
+                    });
+                    opened
+                        || segments.any(|segment| IO_TYPES.iter().any(|name| segment.ident == name))
+                }
+                _ => false,
+            },
+            Expr::MethodCall(call) => {
+                ["accept", "into_split", "try_clone"]
+                    .iter()
+                    .any(|method| call.method == method)
+                    && self.is_bound(&call.receiver)
+            }
+            expr => self.is_bound(expr),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for IoBindings {
+    fn visit_local(&mut self, local: &'ast Local) {
+        visit::visit_local(self, local);
+        let (pat, ty) = match &local.pat {
+            Pat::Type(pat) => (&*pat.pat, Some(&*pat.ty)),
+            pat => (pat, None),
+        };
+        let is_io = ty.is_some_and(|ty| self.is_io_type(ty))
+            || local
+                .init
+                .as_ref()
+                .is_some_and(|(_, init)| self.is_io(init));
+        if !is_io {
+            return;
+        }
+        // the halves of `split`, or the stream of `accept`
+        match pat {
+            Pat::Tuple(tuple) => self
+                .bindings
+                .extend(tuple.elems.iter().filter_map(thread::pat_ident).cloned()),
+            pat => self.bindings.extend(thread::pat_ident(pat).cloned()),
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast Item) {}
+}
+
+impl VisitMut for IoBindings {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_mut::visit_expr_mut(self, node);
+        let call = match node {
+            Expr::MethodCall(call) if self.is_bound(&call.receiver) => call,
+            _ => return,
+        };
+        let method = call.method.to_string();
+        let hint = match method.as_str() {
+            "into_split" if call.args.is_empty() => {
+                "use `try_clone` of the std type for a second handle"
+            }
+            "shutdown" if call.args.is_empty() => {
+                "use `flush`, or `shutdown(std::net::Shutdown::Write)` of a std socket"
+            }
+            method if is_number_io(method) && method.starts_with("read_") => {
+                "use `read_exact` and `from_be_bytes`"
+            }
+            method if is_number_io(method) => "use `to_be_bytes` and `write_all`",
+            _ => return,
+        };
+        *node = error_expr(&unsupported_method(call, hint));
+    }
+
+    fn visit_item_mut(&mut self, _node: &mut Item) {}
+}
+
+/// Check that the handles of spawned tasks are joined, and that I/O values
+/// only call methods with a blocking equivalent, like `shutdown` or
+/// `read_u32` of a `TcpStream`. Methods of the same name on other values are
+/// kept.
+fn check_fn(sig: &Signature, block: &mut Block) {
+    thread::check_handles(block);
+    let mut io = IoBindings::new(sig);
+    io.visit_block(block);
+    if !io.bindings.is_empty() {
+        io.visit_block_mut(block);
+    }
+}
+
+/// `handle.await` to the `Result` of joining the thread
+fn join(handle: &Expr) -> Expr {
+    parse_quote!(#handle.join())
+}
+
+/// `rx.await` on a oneshot receiver
+fn recv(rx: &Expr) -> Expr {
+    parse_quote!(#rx.recv())
+}
+
+/// `rx.recv().await` on an mpsc receiver to an `Option<T>`
+fn received(recv: &Expr) -> Expr {
+    parse_quote!(#recv.ok())
+}
+
+/// Awaiting a `JoinHandle` bound with `let handle = tokio::spawn(..)` joins
+/// the thread, and awaiting the receiver of `let (tx, rx) = oneshot::channel()`
+/// receives from it. Receivers of std channels need not be mutable.
+fn convert_block(block: &mut Block) {
+    let mut bindings = AwaitBindings::default();
+    bindings.bind_handles(block, join);
+    for stmt in &mut block.stmts {
+        let local = match stmt {
+            Stmt::Local(local) => local,
+            _ => continue,
+        };
+        let is_channel = match local.init.as_ref().map(|(_, init)| &**init) {
+            Some(Expr::Call(call)) => thread::called_fn(call)
+                .is_some_and(|name| name == "channel" || name == "sync_channel"),
+            _ => false,
+        };
+        let pat = match &mut local.pat {
+            Pat::Type(pat) => &mut *pat.pat,
+            pat => pat,
+        };
+        if let (true, Pat::Tuple(tuple)) = (is_channel, pat) {
+            if let Some(Pat::Ident(rx)) = tuple.elems.iter_mut().nth(1) {
+                rx.mutability = None;
+                bindings.bind(&rx.ident, recv);
+            }
+        }
+    }
+    bindings.convert(block);
+}
+
+fn convert_fn(sig: &Signature, block: &mut Block) {
+    thread::convert_sync(sig, block, Some(received));
+}
@@ -0,0 +1,211 @@
+use std::collections::HashSet;
+
+use syn::{
+    parse_quote,
+    visit_mut::{self, VisitMut},
+    Block, Expr, ExprCall, FnArg, Ident, Pat, Signature, Stmt, Type,
+};
+
+use super::thread::{called_fn, pat_ident};
+use super::{method_call, produced_by, unsupported_method, Preset};
+use crate::visit::error_expr;
+
+/// `tokio_tungstenite` to `tungstenite`
+pub const TUNGSTENITE: Preset = Preset {
+    name: "tungstenite",
+    replace: &[
+        ("tokio_tungstenite::tungstenite", "tungstenite"),
+        (
+            "tokio_tungstenite::WebSocketStream",
+            "tungstenite::WebSocket",
+        ),
+        (
+            "tokio_tungstenite::MaybeTlsStream",
+            "tungstenite::stream::MaybeTlsStream",
+        ),
+        ("tokio_tungstenite::connect_async", "tungstenite::connect"),
+        ("tokio_tungstenite::accept_async", "tungstenite::accept"),
+        (
+            "tokio_tungstenite::accept_hdr_async",
+            "tungstenite::accept_hdr",
+        ),
+        ("tokio_tungstenite::client_async", "tungstenite::client"),
+    ],
+    unsupported: &[(
+        "tokio_tungstenite::connect_async_with_config",
+        "use `tungstenite::client::connect_with_config`",
+    )],
+    convert_expr,
+    convert_block,
+    convert_fn,
+    check_fn: |_, _| {},
+    no_std: false,
+};
+
+/// names of the handshakes, as converted or imported under their async name
+const HANDSHAKES: &[&str] = &[
+    "accept",
+    "accept_hdr",
+    "client",
+    "accept_async",
+    "accept_hdr_async",
+    "client_async",
+];
+
+/// names of the functions opening a WebSocket, as converted or imported under
+/// their async name
+const CONSTRUCTORS: &[&str] = &[
+    "connect",
+    "connect_async",
+    "accept",
+    "accept_hdr",
+    "client",
+    "accept_async",
+    "accept_hdr_async",
+    "client_async",
+];
+
+/// whether a call is a `tungstenite` handshake failing with a `HandshakeError`
+fn is_handshake(call: &ExprCall) -> bool {
+    let path = match &*call.func {
+        Expr::Path(func) if func.qself.is_none() => &func.path,
+        _ => return false,
+    };
+    let names: Vec<_> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+    match names.as_slice() {
+        [name] => name.ends_with("_async") && HANDSHAKES.contains(&name.as_str()),
+        [krate, name] => krate == "tungstenite" && HANDSHAKES.contains(&name.as_str()),
+        _ => false,
+    }
+}
+
+fn convert_expr(node: &mut Expr) -> syn::Result<()> {
+    let call = match node {
+        Expr::Call(call) => call,
+        _ => return Ok(()),
+    };
+    // a handshake on a blocking stream is never interrupted, its failure is
+    // the error of the async handshake
+    if is_handshake(call) {
+        *node = parse_quote!(#call.map_err(|error| match error {
+            ::tungstenite::HandshakeError::Failure(error) => error,
+            ::tungstenite::HandshakeError::Interrupted(_) => {
+                ::tungstenite::Error::Io(::std::io::ErrorKind::WouldBlock.into())
+            }
+        }));
+        return Ok(());
+    }
+    // `SinkExt::send(&mut ws, message)`
+    let is_sink_send = match &*call.func {
+        Expr::Path(func) => {
+            let mut segments = func.path.segments.iter().rev();
+            matches!(
+                (segments.next(), segments.next()),
+                (Some(method), Some(sink)) if method.ident == "send" && sink.ident == "SinkExt"
+            )
+        }
+        _ => false,
+    };
+    if is_sink_send {
+        let args = &call.args;
+        *node = parse_quote!(::tungstenite::WebSocket::send(#args));
+    }
+    Ok(())
+}
+
+/// Reading and writing the WebSockets bound to the given names.
+struct WebSockets(HashSet<Ident>);
+
+impl WebSockets {
+    fn convert(mut self, block: &mut Block) {
+        if !self.0.is_empty() {
+            self.visit_block_mut(block);
+        }
+    }
+
+    fn contains(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| self.0.contains(ident)),
+            _ => false,
+        }
+    }
+}
+
+impl VisitMut for WebSockets {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_mut::visit_expr_mut(self, node);
+        if let Some(call) = method_call(node, &["split"], 0) {
+            if self.contains(&call.receiver) {
+                let error = unsupported_method(call, "read and write with the same `WebSocket`");
+                *node = error_expr(&error);
+            }
+            return;
+        }
+        // `ws.next().await` to the next message, `None` once closed
+        let ws = match node {
+            Expr::Await(expr) => match method_call(&expr.base, &["next"], 0) {
+                Some(call) if self.contains(&call.receiver) => &call.receiver,
+                _ => return,
+            },
+            _ => return,
+        };
+        *node = parse_quote!(match #ws.read() {
+            ::core::result::Result::Err(
+                ::tungstenite::Error::ConnectionClosed | ::tungstenite::Error::AlreadyClosed,
+            ) => ::core::option::Option::None,
+            result => ::core::option::Option::Some(result),
+        });
+    }
+}
+
+/// the bindings of `let (ws, _) = connect_async(url).await?` and the like
+fn convert_block(block: &mut Block) {
+    let mut websockets = WebSockets(HashSet::new());
+    for stmt in &block.stmts {
+        let local = match stmt {
+            Stmt::Local(local) => local,
+            _ => continue,
+        };
+        let opened = local.init.as_ref().is_some_and(|(_, init)| {
+            matches!(produced_by(init), Expr::Call(call) if called_fn(call)
+                .is_some_and(|name| CONSTRUCTORS.iter().any(|constructor| name == constructor)))
+        });
+        if !opened {
+            continue;
+        }
+        let pat = match &local.pat {
+            Pat::Tuple(tuple) => tuple.elems.first(),
+            pat => Some(pat),
+        };
+        websockets.0.extend(pat.and_then(pat_ident).cloned());
+    }
+    websockets.convert(block);
+}
+
+/// the parameters of type `WebSocketStream<S>` or a reference to it
+fn convert_fn(sig: &Signature, block: &mut Block) {
+    let mut websockets = WebSockets(HashSet::new());
+    for input in &sig.inputs {
+        let input = match input {
+            FnArg::Typed(input) => input,
+            FnArg::Receiver(_) => continue,
+        };
+        let mut ty = &*input.ty;
+        while let Type::Reference(reference) = ty {
+            ty = &reference.elem;
+        }
+        let is_websocket = match ty {
+            Type::Path(ty) => ty.path.segments.last().is_some_and(|segment| {
+                segment.ident == "WebSocket" || segment.ident == "WebSocketStream"
+            }),
+            _ => false,
+        };
+        if is_websocket {
+            websockets.0.extend(pat_ident(&input.pat).cloned());
+        }
+    }
+    websockets.convert(block);
+}
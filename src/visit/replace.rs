@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use syn::{parse_quote, punctuated::Punctuated, Expr, Item, ItemUse, Path, Stmt, UseTree};
+
+use crate::parse::Replacement;
+use crate::visit::error_expr;
+use crate::visit::preset::Preset;
+
+/// Substitute paths according to the `replace(from => to, ..)` table, and
+/// the ones of presets.
+pub struct Replacer {
+    entries: Vec<Replacement>,
+    /// whether each entry matched some path
+    used: Vec<bool>,
+    /// path prefixes with no blocking counterpart, with the preset they come
+    /// from and a hint
+    unsupported: Vec<(Path, &'static str, &'static str)>,
+    /// uses of unsupported paths
+    errors: Vec<syn::Error>,
+    /// names brought in scope by imports, and the paths they import
+    imports: HashMap<String, Vec<Ident>>,
+}
+
+impl Replacer {
+    pub fn new(entries: Vec<Replacement>) -> Self {
+        let used = vec![false; entries.len()];
+        Self {
+            entries,
+            used,
+            unsupported: vec![],
+            errors: vec![],
+            imports: HashMap::new(),
+        }
+    }
+
+    /// Add the paths of a preset.
+    pub fn add_preset(&mut self, preset: &Preset) {
+        for (from, to) in preset.replace {
+            self.entries.push(Replacement {
+                from: syn::parse_str(from).unwrap(),
+                to: syn::parse_str(to).unwrap(),
+            });
+            // only entries given by the user are expected to match
+            self.used.push(true);
+        }
+        for (path, hint) in preset.unsupported {
+            self.unsupported
+                .push((syn::parse_str(path).unwrap(), preset.name, *hint));
+        }
+    }
+
+    /// The path with its first segment expanded if it is an imported name,
+    /// along with the length of the imported path, or 0 if it is not imported.
+    fn resolve(&self, path: &Path) -> (Vec<Ident>, usize) {
+        let idents = path.segments.iter().map(|segment| segment.ident.clone());
+        let imported = match path.segments.first() {
+            Some(first) if path.leading_colon.is_none() => {
+                self.imports.get(&first.ident.to_string())
+            }
+            _ => None,
+        };
+        match imported {
+            Some(imported) => {
+                let resolved = imported.iter().cloned().chain(idents.skip(1)).collect();
+                (resolved, imported.len())
+            }
+            None => (idents.collect(), 0),
+        }
+    }
+
+    /// An error if the path starts with an unsupported prefix longer than
+    /// `imported`, the length of the import it goes through.
+    fn unsupported_error(
+        &self,
+        idents: &[Ident],
+        imported: usize,
+        tokens: impl ToTokens,
+    ) -> Option<syn::Error> {
+        let (prefix, preset, hint) = self.unsupported.iter().find(|(prefix, _, _)| {
+            prefix.segments.len() > imported && starts_with(idents, prefix)
+        })?;
+        let message = format!(
+            "`{}` from preset `{}` has no blocking equivalent: {}",
+            path_to_string(prefix),
+            preset,
+            hint
+        );
+        Some(syn::Error::new_spanned(tokens, message))
+    }
+
+    /// Replace an unsupported path in an expression by a compile error, which
+    /// spares the errors of the path staying in place. This includes the uses
+    /// of an unsupported import, whose error is already reported.
+    pub fn check_expr(&self, node: &mut Expr) {
+        // a call is replaced as a whole, its arguments would be left over
+        let path = match node {
+            Expr::Path(expr) => &expr.path,
+            Expr::Call(call) => match &*call.func {
+                Expr::Path(func) => &func.path,
+                _ => return,
+            },
+            _ => return,
+        };
+        let (idents, imported) = self.resolve(path);
+        let imported = imported.saturating_sub(1);
+        if let Some(error) = self.unsupported_error(&idents, imported, path) {
+            *node = error_expr(&error);
+        }
+    }
+
+    /// The entry with the longest `from` that is a prefix of `idents`, and
+    /// longer than `imported`, the length of the import it goes through. An
+    /// import already substituted covers the shorter ones.
+    fn find(&mut self, idents: &[Ident], imported: usize) -> Option<&Replacement> {
+        let (index, _) = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                entry.from.segments.len() > imported && starts_with(idents, &entry.from)
+            })
+            .max_by_key(|(_, entry)| entry.from.segments.len())?;
+        self.used[index] = true;
+        Some(&self.entries[index])
+    }
+
+    /// Substitute the prefix of a path in a type, an expression or a trait
+    /// bound. Generic arguments following the prefix are kept.
+    pub fn replace_path(&mut self, path: &mut Path) {
+        let (idents, imported) = self.resolve(path);
+        if let Some(error) = self.unsupported_error(&idents, imported, &*path) {
+            self.errors.push(error);
+            return;
+        }
+        let (from, to) = match self.find(&idents, imported) {
+            Some(entry) => (entry.from.segments.len(), entry.to.clone()),
+            None => return,
+        };
+        // the first segment of the path stands for the whole import
+        let matched = from - imported.saturating_sub(1);
+        let mut replaced = to;
+        let mut rest = std::mem::take(&mut path.segments)
+            .into_iter()
+            .skip(matched - 1);
+        if let (Some(last), Some(matched)) = (replaced.segments.last_mut(), rest.next()) {
+            last.arguments = matched.arguments;
+        }
+        replaced.segments.extend(rest);
+        *path = replaced;
+    }
+
+    /// Split the imports matching an entry out of a use tree, as the use trees
+    /// of the substituted paths. Return `false` if nothing is left in the tree.
+    fn split_use_tree(
+        &mut self,
+        tree: &mut UseTree,
+        prefix: &mut Vec<Ident>,
+        moved: &mut Vec<UseTree>,
+    ) -> bool {
+        // the imported path, and the name it is imported as
+        let (imported, name) = match tree {
+            UseTree::Path(path) => {
+                prefix.push(path.ident.clone());
+                let keep = self.split_use_tree(&mut path.tree, prefix, moved);
+                prefix.pop();
+                return keep;
+            }
+            UseTree::Group(group) => {
+                let items = std::mem::take(&mut group.items);
+                let mut kept = Punctuated::new();
+                for mut item in items {
+                    if self.split_use_tree(&mut item, prefix, moved) {
+                        kept.push(item);
+                    }
+                }
+                group.items = kept;
+                return !group.items.is_empty();
+            }
+            UseTree::Glob(glob) => {
+                if let Some(error) = self.unsupported_error(prefix, 0, &*glob) {
+                    self.errors.push(error);
+                    return true;
+                }
+                if let Some(entry) = self.find(prefix, 0) {
+                    let path = substitute(entry, prefix);
+                    moved.push(parse_quote!(#path::*));
+                    return false;
+                }
+                return true;
+            }
+            // `self` in `use a::b::{self}` imports `a::b` itself
+            UseTree::Name(name) if name.ident == "self" => (prefix.clone(), prefix.last().cloned()),
+            UseTree::Name(name) => {
+                let mut imported = prefix.clone();
+                imported.push(name.ident.clone());
+                (imported, Some(name.ident.clone()))
+            }
+            UseTree::Rename(rename) => {
+                let mut imported = prefix.clone();
+                imported.push(rename.ident.clone());
+                (imported, Some(rename.rename.clone()))
+            }
+        };
+        if let Some(name) = &name {
+            if name != "_" {
+                self.imports.insert(name.to_string(), imported.clone());
+            }
+        }
+        if let Some(error) = self.unsupported_error(&imported, 0, &*tree) {
+            self.errors.push(error);
+            return true;
+        }
+        let entry = match self.find(&imported, 0) {
+            Some(entry) => entry,
+            None => return true,
+        };
+        let path = substitute(entry, &imported);
+        let renamed = path.segments.last().map(|segment| &segment.ident) != name.as_ref();
+        moved.push(match name {
+            Some(name) if renamed => parse_quote!(#path as #name),
+            _ => parse_quote!(#path),
+        });
+        false
+    }
+
+    /// The use items substituting the imports split out of a use item.
+    fn replace_use(&mut self, item: &mut ItemUse) -> (bool, Vec<Item>) {
+        let mut moved = vec![];
+        let keep = self.split_use_tree(&mut item.tree, &mut vec![], &mut moved);
+        let ItemUse { attrs, vis, .. } = &*item;
+        let items = moved
+            .into_iter()
+            .map(|tree| parse_quote!(#(#attrs)* #vis use #tree;))
+            .collect();
+        (keep, items)
+    }
+
+    /// Substitute the imports among the items of a module.
+    pub fn replace_imports(&mut self, items: &mut Vec<Item>) {
+        let old = std::mem::take(items);
+        for mut item in old {
+            let (keep, replaced) = match &mut item {
+                Item::Use(item_use) => self.replace_use(item_use),
+                _ => (true, vec![]),
+            };
+            if keep {
+                items.push(item);
+            }
+            items.extend(replaced);
+        }
+    }
+
+    /// Substitute the imports among the statements of a block.
+    pub fn replace_stmt_imports(&mut self, stmts: &mut Vec<Stmt>) {
+        let old = std::mem::take(stmts);
+        for mut stmt in old {
+            let (keep, replaced) = match &mut stmt {
+                Stmt::Item(Item::Use(item_use)) => self.replace_use(item_use),
+                _ => (true, vec![]),
+            };
+            if keep {
+                stmts.push(stmt);
+            }
+            stmts.extend(replaced.into_iter().map(Stmt::Item));
+        }
+    }
+
+    /// Errors for the unsupported paths and the entries that matched nothing.
+    pub fn errors(&self) -> TokenStream2 {
+        let unsupported = self.errors.iter().map(syn::Error::to_compile_error);
+        let unused = self
+            .entries
+            .iter()
+            .zip(&self.used)
+            .filter(|(_, used)| !**used)
+            .map(|(entry, _)| {
+                let from = &entry.from;
+                let message = format!("`{}` in `replace` matches no path", path_to_string(from));
+                syn::Error::new_spanned(from, message).to_compile_error()
+            });
+        quote!(#(#unsupported)* #(#unused)*)
+    }
+}
+
+/// whether `prefix` is a prefix of `idents`
+fn starts_with(idents: &[Ident], prefix: &Path) -> bool {
+    prefix.segments.len() <= idents.len()
+        && prefix
+            .segments
+            .iter()
+            .zip(idents)
+            .all(|(segment, ident)| segment.ident == *ident)
+}
+
+fn path_to_string(path: &Path) -> String {
+    quote!(#path).to_string().replace(' ', "")
+}
+
+/// The imported path with the `from` prefix of the entry substituted by `to`,
+/// without the leading `::` that use trees cannot have.
+fn substitute(entry: &Replacement, imported: &[Ident]) -> Path {
+    let mut path = entry.to.clone();
+    path.leading_colon = None;
+    for ident in &imported[entry.from.segments.len()..] {
+        path.segments.push(ident.clone().into());
+    }
+    path
+}
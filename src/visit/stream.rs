@@ -0,0 +1,489 @@
+use proc_macro2::{Ident, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::{
+    parse::Parser,
+    parse_quote,
+    visit::{self, Visit},
+    visit_mut::{self, VisitMut},
+    Block, Error, Expr, ExprAsync, ExprBreak, ExprClosure, ExprLoop, ExprMethodCall, ExprReturn,
+    ExprTry, GenericArgument, Item, Macro, Path, PathArguments, ReturnType, Signature, Stmt,
+    TraitBound, Type, TypeParamBound,
+};
+
+use crate::visit::error_expr;
+use crate::visit::future::{is_box_pin, unbox_future};
+use crate::visit::preset::needs_std;
+
+/// whether a path is `stream::$name`, like `futures::stream::iter`
+fn is_stream_fn(path: &Path, name: &str) -> bool {
+    let mut segments = path.segments.iter().rev();
+    matches!(
+        (segments.next(), segments.next()),
+        (Some(func), Some(module)) if func.ident == name && module.ident == "stream"
+    )
+}
+
+/// whether a macro invocation is `async_stream::stream!` or `try_stream!`
+fn stream_macro(mac: &Macro) -> Option<bool> {
+    let segment = mac.path.segments.last()?;
+    if mac.path.segments.len() > 1 && mac.path.segments[0].ident != "async_stream" {
+        return None;
+    }
+    if segment.ident == "stream" {
+        Some(false)
+    } else if segment.ident == "try_stream" {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// whether an expression builds a stream, judging from the syntax only
+fn is_stream_expr(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(call) => match &*call.func {
+            Expr::Path(func) => func
+                .path
+                .segments
+                .iter()
+                .rev()
+                .nth(1)
+                .is_some_and(|module| module.ident == "stream"),
+            _ => false,
+        },
+        Expr::MethodCall(call) => {
+            matches!(
+                call.method.to_string().as_str(),
+                "buffered" | "buffer_unordered" | "try_next" | "try_collect"
+            ) || is_then_async(call)
+                || is_stream_expr(&call.receiver)
+        }
+        Expr::Macro(mac) => stream_macro(&mac.mac).is_some(),
+        Expr::Paren(paren) => is_stream_expr(&paren.expr),
+        Expr::Group(group) => is_stream_expr(&group.expr),
+        _ => false,
+    }
+}
+
+/// whether a method call is `.then(|x| async { .. })`
+fn is_then_async(call: &ExprMethodCall) -> bool {
+    call.method == "then"
+        && match call.args.first() {
+            Some(Expr::Closure(closure)) if call.args.len() == 1 => {
+                closure.asyncness.is_some() || matches!(&*closure.body, Expr::Async(_))
+            }
+            _ => false,
+        }
+}
+
+/// The `Iterator` counterpart of a `Stream` or `TryStream` trait bound.
+fn iterator_bound(bound: &TraitBound) -> Option<Path> {
+    let segment = bound.path.segments.last()?;
+    let mut path: Path = parse_quote!(::core::iter::Iterator);
+    if segment.ident == "Stream" {
+        path.segments.last_mut()?.arguments = segment.arguments.clone();
+        return Some(path);
+    }
+    if segment.ident != "TryStream" {
+        return None;
+    }
+    // `TryStream<Ok = T, Error = E>` to `Iterator<Item = Result<T, E>>`
+    let mut ok = None;
+    let mut error = None;
+    if let PathArguments::AngleBracketed(args) = &segment.arguments {
+        for arg in &args.args {
+            match arg {
+                GenericArgument::Binding(binding) if binding.ident == "Ok" => {
+                    ok = Some(&binding.ty)
+                }
+                GenericArgument::Binding(binding) if binding.ident == "Error" => {
+                    error = Some(&binding.ty)
+                }
+                _ => {}
+            }
+        }
+    }
+    if let (Some(ok), Some(error)) = (ok, error) {
+        path = parse_quote!(::core::iter::Iterator<Item = ::core::result::Result<#ok, #error>>);
+    }
+    Some(path)
+}
+
+/// Convert `Stream<Item = T>` and `TryStream<Ok = T, Error = E>` bounds, in
+/// `impl Trait`, `dyn Trait`, generics and where clauses, to `Iterator`.
+pub fn convert_bound(bound: &mut TraitBound) {
+    if let Some(path) = iterator_bound(bound) {
+        bound.path = path;
+    }
+}
+
+/// whether a type is a boxed `dyn Stream`
+fn is_boxed_stream(ty: &Type) -> bool {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return false,
+    };
+    let segment = match path.segments.last() {
+        Some(segment) if segment.ident == "Box" => segment,
+        _ => return false,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| match arg {
+            GenericArgument::Type(Type::TraitObject(object)) => {
+                object.bounds.iter().any(|bound| match bound {
+                    TypeParamBound::Trait(bound) => iterator_bound(bound).is_some(),
+                    _ => false,
+                })
+            }
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+/// The boxed iterator counterpart of a boxed stream type, that is
+///
+/// - `BoxStream<'a, T>` to `Box<dyn Iterator<Item = T> + Send + 'a>`
+/// - `LocalBoxStream<'a, T>` to `Box<dyn Iterator<Item = T> + 'a>`
+/// - `Pin<Box<dyn Stream<Item = T>>>` to `Box<dyn Stream<Item = T>>`, whose
+///   bound is converted later on
+///
+/// The `Box` of the first two comes from `std`, so they are errors when the
+/// preset `no_std` keeps the blocking version `no_std`.
+fn boxed_iterator(ty: &Type, no_std: Option<&str>) -> Option<Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().collect::<Vec<_>>(),
+        _ => return None,
+    };
+    let lifetime = args.iter().find_map(|arg| match arg {
+        GenericArgument::Lifetime(lifetime) => Some(quote!(+ #lifetime)),
+        _ => None,
+    });
+    let item = args.iter().rev().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    });
+    let boxed = segment.ident == "BoxStream" || segment.ident == "LocalBoxStream";
+    match (item, no_std) {
+        (Some(_), Some(preset)) if boxed => {
+            let what = format!("`{}`", segment.ident);
+            let error = needs_std(&segment.ident, &what, preset);
+            Some(Type::Verbatim(error.to_compile_error()))
+        }
+        (Some(item), None) if segment.ident == "BoxStream" => Some(parse_quote!(
+            ::std::boxed::Box<dyn ::core::iter::Iterator<Item = #item> + Send #lifetime>
+        )),
+        (Some(item), None) if segment.ident == "LocalBoxStream" => Some(parse_quote!(
+            ::std::boxed::Box<dyn ::core::iter::Iterator<Item = #item> #lifetime>
+        )),
+        (Some(inner), _) if segment.ident == "Pin" && is_boxed_stream(inner) => {
+            Some((*inner).clone())
+        }
+        _ => None,
+    }
+}
+
+/// Convert boxed stream types to boxed iterators.
+pub fn convert_type(ty: &mut Type, no_std: Option<&str>) {
+    if let Some(converted) = boxed_iterator(ty, no_std) {
+        *ty = converted;
+    }
+}
+
+/// Replace `yield x` by pushing `x` into a vector.
+struct YieldToPush;
+
+impl VisitMut for YieldToPush {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_mut::visit_expr_mut(self, node);
+        if let Expr::Yield(expr) = node {
+            let value = match &expr.expr {
+                Some(value) => quote!(#value),
+                None => quote!(()),
+            };
+            *node = parse_quote!(__amphi_items.push(#value));
+        }
+    }
+
+    // `yield` only belongs to the stream body itself
+    fn visit_expr_closure_mut(&mut self, _node: &mut ExprClosure) {}
+
+    fn visit_expr_async_mut(&mut self, _node: &mut ExprAsync) {}
+
+    fn visit_item_mut(&mut self, _node: &mut Item) {}
+}
+
+/// whether a token stream contains `break`, `return` or `?`
+fn exits_tokens(tokens: &TokenStream2) -> bool {
+    tokens.clone().into_iter().any(|token| match token {
+        TokenTree::Punct(punct) => punct.as_char() == '?',
+        TokenTree::Ident(ident) => ident == "break" || ident == "return",
+        TokenTree::Group(group) => exits_tokens(&group.stream()),
+        TokenTree::Literal(_) => false,
+    })
+}
+
+/// Find `break`, `return` and `?`, anything that may end a loop.
+struct Exits {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for Exits {
+    fn visit_expr_break(&mut self, node: &'ast ExprBreak) {
+        self.found = true;
+        visit::visit_expr_break(self, node);
+    }
+
+    fn visit_expr_return(&mut self, node: &'ast ExprReturn) {
+        self.found = true;
+        visit::visit_expr_return(self, node);
+    }
+
+    fn visit_expr_try(&mut self, node: &'ast ExprTry) {
+        self.found = true;
+        visit::visit_expr_try(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast Macro) {
+        self.found |= exits_tokens(&node.tokens);
+    }
+}
+
+/// Find a `loop` of the stream body that never ends, i.e. without any
+/// `break`, `return` or `?` inside.
+struct Unbounded<'ast> {
+    found: Option<&'ast ExprLoop>,
+}
+
+impl<'ast> Visit<'ast> for Unbounded<'ast> {
+    fn visit_expr_loop(&mut self, node: &'ast ExprLoop) {
+        let mut exits = Exits { found: false };
+        exits.visit_block(&node.body);
+        if !exits.found && self.found.is_none() {
+            self.found = Some(node);
+        }
+        visit::visit_expr_loop(self, node);
+    }
+
+    // nested closures, async blocks and items are not the stream body
+    fn visit_expr_closure(&mut self, _node: &'ast ExprClosure) {}
+
+    fn visit_expr_async(&mut self, _node: &'ast ExprAsync) {}
+
+    fn visit_item(&mut self, _node: &'ast Item) {}
+}
+
+/// Lower `stream! { .. }` and `try_stream! { .. }` from `async_stream`.
+///
+/// The body runs to completion up front, and the yielded items are collected
+/// into a vector, which is turned into an iterator. The body runs in a closure,
+/// so that `return` ends the stream, and for `try_stream!`, an error stopping
+/// the body is yielded after the items produced before it. A body looping
+/// forever would never return, so it is an error.
+fn lower_stream_macro(tokens: TokenStream2, fallible: bool) -> Option<Expr> {
+    let mut stmts = Block::parse_within.parse2(tokens).ok()?;
+    let mut unbounded = Unbounded { found: None };
+    for stmt in &stmts {
+        unbounded.visit_stmt(stmt);
+    }
+    if let Some(endless) = unbounded.found {
+        let error = Error::new_spanned(
+            endless.loop_token,
+            "a `loop` without `break`, `return` or `?` never ends in the blocking version, \
+             where the stream body runs to completion up front; use `stream::unfold` or \
+             `stream::repeat_with` for an endless stream",
+        );
+        return Some(error_expr(&error));
+    }
+    for stmt in &mut stmts {
+        YieldToPush.visit_stmt_mut(stmt);
+    }
+    let expr = if fallible {
+        parse_quote!({
+            let mut __amphi_items = ::std::vec::Vec::new();
+            let __amphi_result: ::core::result::Result<(), _> = (|| {
+                #(#stmts)*
+                #[allow(unreachable_code)]
+                ::core::result::Result::Ok(())
+            })();
+            __amphi_items
+                .into_iter()
+                .map(::core::result::Result::Ok)
+                .chain(__amphi_result.err().map(::core::result::Result::Err))
+        })
+    } else {
+        parse_quote!({
+            let mut __amphi_items = ::std::vec::Vec::new();
+            (|| {
+                #(#stmts)*
+            })();
+            __amphi_items.into_iter()
+        })
+    };
+    Some(expr)
+}
+
+/// whether a macro invocation is `stream!` or `try_stream!`, which are
+/// lowered as expressions
+pub fn is_stream_macro(mac: &Macro) -> bool {
+    stream_macro(mac).is_some()
+}
+
+/// Lower `unfold(init, f)`, where `f` returns `Option<(item, state)>` once
+/// converted, to `from_fn` threading the state.
+fn lower_unfold(init: &Expr, f: &Expr) -> Expr {
+    parse_quote!({
+        let mut __amphi_state = ::core::option::Option::Some(#init);
+        let mut __amphi_next = #f;
+        ::core::iter::from_fn(move || {
+            let (item, state) = __amphi_next(__amphi_state.take()?)?;
+            __amphi_state = ::core::option::Option::Some(state);
+            ::core::option::Option::Some(item)
+        })
+    })
+}
+
+/// `Box::new(stream)` from `Box::pin(stream)`, naming `Box` the same way,
+/// or from `stream.boxed()` and `stream.boxed_local()`, which use the `Box`
+/// of `std`.
+fn box_stream(expr: &Expr, no_std: Option<&str>) -> Option<Expr> {
+    match expr {
+        Expr::Call(call) if call.args.len() == 1 => match &*call.func {
+            Expr::Path(func) if is_box_pin(&func.path) => {
+                let mut func = func.clone();
+                let new = func.path.segments.last_mut()?;
+                new.ident = Ident::new("new", new.ident.span());
+                let stream = &call.args[0];
+                Some(parse_quote!(#func(#stream)))
+            }
+            _ => None,
+        },
+        Expr::MethodCall(call)
+            if call.args.is_empty() && (call.method == "boxed" || call.method == "boxed_local") =>
+        {
+            let receiver = &call.receiver;
+            Some(match no_std {
+                Some(preset) => {
+                    let what = format!("`{}` of a stream", call.method);
+                    error_expr(&needs_std(&call.method, &what, preset))
+                }
+                None => parse_quote!(::std::boxed::Box::new(#receiver)),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Convert stream constructors and `StreamExt` / `TryStreamExt` combinators
+/// to their `Iterator` counterparts.
+///
+/// This runs before nested expressions are converted, so async closures and
+/// blocks given to the combinators can still be told apart. The preset
+/// `no_std` keeping the blocking version `no_std` rules out the conversions
+/// needing allocation.
+pub fn convert_expr(node: &mut Expr, no_std: Option<&str>) {
+    // `Box::pin(stream)` and `stream.boxed()` to `Box::new(stream)`
+    if unbox_future(node).is_some_and(is_stream_expr) {
+        if let Some(boxed) = box_stream(node, no_std) {
+            *node = boxed;
+        }
+        return;
+    }
+    let converted: Expr = match node {
+        Expr::Macro(mac) => match (stream_macro(&mac.mac), no_std) {
+            (Some(_), Some(preset)) => {
+                let what = format!("`{}!`", mac.mac.path.segments.last().unwrap().ident);
+                error_expr(&needs_std(&mac.mac.path, &what, preset))
+            }
+            (Some(fallible), None) => match lower_stream_macro(mac.mac.tokens.clone(), fallible) {
+                Some(expr) => expr,
+                None => return,
+            },
+            (None, _) => return,
+        },
+        Expr::Call(call) => {
+            let path = match &*call.func {
+                Expr::Path(func) if func.qself.is_none() => &func.path,
+                _ => return,
+            };
+            let args: Vec<&Expr> = call.args.iter().collect();
+            match args.as_slice() {
+                [iter] if is_stream_fn(path, "iter") => {
+                    parse_quote!(::core::iter::IntoIterator::into_iter(#iter))
+                }
+                [init, f] if is_stream_fn(path, "unfold") => lower_unfold(init, f),
+                [] if is_stream_fn(path, "empty") => parse_quote!(::core::iter::empty()),
+                [value] if is_stream_fn(path, "once") => parse_quote!(::core::iter::once(#value)),
+                [value] if is_stream_fn(path, "repeat") => {
+                    parse_quote!(::core::iter::repeat(#value))
+                }
+                [f] if is_stream_fn(path, "repeat_with") => {
+                    parse_quote!(::core::iter::repeat_with(#f))
+                }
+                _ => return,
+            }
+        }
+        Expr::MethodCall(call) => {
+            let receiver = &call.receiver;
+            let args = &call.args;
+            match call.method.to_string().as_str() {
+                "then" if is_then_async(call) => {
+                    call.method = parse_quote!(map);
+                    return;
+                }
+                // items are already values, nothing left to buffer
+                "buffered" | "buffer_unordered" if args.len() == 1 => (**receiver).clone(),
+                "try_next" if args.is_empty() => parse_quote!(#receiver.next().transpose()),
+                "try_collect" if args.is_empty() => {
+                    let collection = match &call.turbofish {
+                        Some(turbofish) => {
+                            let collection = &turbofish.args;
+                            quote!(#collection)
+                        }
+                        None => quote!(_),
+                    };
+                    parse_quote!(
+                        #receiver.collect::<::core::result::Result<#collection, _>>()
+                    )
+                }
+                "for_each_concurrent" | "try_for_each_concurrent" if args.len() == 2 => {
+                    let method = if call.method == "for_each_concurrent" {
+                        quote!(for_each)
+                    } else {
+                        quote!(try_for_each)
+                    };
+                    let f = &args[1];
+                    parse_quote!(#receiver.#method(#f))
+                }
+                _ => return,
+            }
+        }
+        _ => return,
+    };
+    *node = converted;
+}
+
+/// Box the stream in tail position of a function returning a boxed stream.
+///
+/// The return type becomes a boxed iterator, so the `.boxed()`,
+/// `.boxed_local()` or `Box::pin(..)` producing the return value becomes
+/// `Box::new(..)`, whatever the stream is built from.
+pub fn box_tail(sig: &Signature, block: &mut Block, no_std: Option<&str>) {
+    let returns_stream = match &sig.output {
+        ReturnType::Type(_, ty) => boxed_iterator(ty, no_std).is_some(),
+        ReturnType::Default => false,
+    };
+    if !returns_stream {
+        return;
+    }
+    if let Some(Stmt::Expr(tail)) = block.stmts.last_mut() {
+        if let Some(boxed) = box_stream(tail, no_std) {
+            *tail = boxed;
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use proc_macro2::Ident;
+use syn::{parse_quote, Attribute, Item, ItemTrait, Stmt, Token};
+
+use crate::visit::attr;
+
+/// The name of the trait generated by `#[trait_variant::make(SendFoo: Send)]`.
+/// `#[trait_variant::make(Send)]` rewrites the trait in place instead.
+fn variant_name(attr: &Attribute) -> Option<Ident> {
+    attr.parse_args_with(|input: syn::parse::ParseStream| {
+        let ident: Ident = input.parse()?;
+        let variant = if input.peek(Token![:]) {
+            Some(ident)
+        } else {
+            None
+        };
+        input.parse::<proc_macro2::TokenStream>()?;
+        Ok(variant)
+    })
+    .ok()
+    .flatten()
+}
+
+/// Drop `#[trait_variant::make(..)]` from a trait, since a blocking trait
+/// has no futures to require `Send` from. Return aliases of the trait for
+/// the variants it would generate, so that they can still be named.
+fn remove_trait_variant(item: &mut ItemTrait) -> Vec<Item> {
+    let ItemTrait { vis, ident, .. } = &*item;
+    let (vis, ident) = (vis.clone(), ident.clone());
+    attr::remove_attribute_macro(&mut item.attrs, "trait_variant", "make")
+        .iter()
+        .filter_map(variant_name)
+        .map(|variant| parse_quote!(#vis use #ident as #variant;))
+        .collect()
+}
+
+/// Drop `trait_variant` from the traits among the items of a module.
+pub fn remove_trait_variants(items: &mut Vec<Item>) {
+    let old = std::mem::take(items);
+    for mut item in old {
+        let aliases = match &mut item {
+            Item::Trait(item_trait) => remove_trait_variant(item_trait),
+            _ => vec![],
+        };
+        items.push(item);
+        items.extend(aliases);
+    }
+}
+
+/// Drop `trait_variant` from the traits among the statements of a block.
+pub fn remove_stmt_trait_variants(stmts: &mut Vec<Stmt>) {
+    let old = std::mem::take(stmts);
+    for mut stmt in old {
+        let aliases = match &mut stmt {
+            Stmt::Item(Item::Trait(item_trait)) => remove_trait_variant(item_trait),
+            _ => vec![],
+        };
+        stmts.push(stmt);
+        stmts.extend(aliases.into_iter().map(Stmt::Item));
+    }
+}
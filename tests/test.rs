@@ -6,9 +6,47 @@ fn ui() {
     t.pass("tests/ui/03-async_only.rs");
     t.pass("tests/ui/04-unit_test_util.rs");
     t.pass("tests/ui/05-differentiate_sync_async.rs");
+    t.pass("tests/ui/06-async_block.rs");
+    t.pass("tests/ui/07-closure.rs");
+    t.pass("tests/ui/08-future_type.rs");
+    t.pass("tests/ui/09-async_trait.rs");
+    t.pass("tests/ui/10-async_recursion.rs");
+    t.pass("tests/ui/11-macro.rs");
+    t.pass("tests/ui/12-stream.rs");
+    t.pass("tests/ui/13-combinator.rs");
+    t.pass("tests/ui/14-join_thread.rs");
+    t.pass("tests/ui/15-async_fn_bound.rs");
+    t.pass("tests/ui/16-async_fn_in_trait.rs");
+    t.pass("tests/ui/17-replace.rs");
+    t.pass("tests/ui/18-preset_tokio.rs");
+    t.pass("tests/ui/19-preset_tokio_task.rs");
+    t.pass("tests/ui/20-preset_async_std.rs");
+    t.pass("tests/ui/21-preset_reqwest.rs");
+    t.pass("tests/ui/22-preset_embedded_hal.rs");
+    t.pass("tests/ui/23-preset_tungstenite.rs");
+    t.pass("tests/ui/24-preset_postgres.rs");
+    t.pass("tests/ui/25-instrument.rs");
+    t.pass("tests/ui/26-tag_predicate.rs");
+    t.pass("tests/ui/27-tag_items.rs");
+    t.pass("tests/ui/28-tag_elements.rs");
+    t.pass("tests/ui/29-tag_cfg_attr.rs");
+    t.pass("tests/ui/30-tag_removal.rs");
+    t.pass("tests/ui/31-tag_attr.rs");
+    t.pass("tests/ui/32-preset_tokio_own_methods.rs");
     t.compile_fail("tests/ui/test_fail/01-sync_only.rs");
     t.compile_fail("tests/ui/test_fail/02-async_only.rs");
-    t.compile_fail("tests/ui/test_fail/03-amphi_option_gt2.rs");
+    t.compile_fail("tests/ui/test_fail/03-amphi_option_duplicated.rs");
     t.compile_fail("tests/ui/test_fail/04-amphi_option_both_async_blocking.rs");
     t.compile_fail("tests/ui/test_fail/05-absolute_path.rs");
+    t.compile_fail("tests/ui/test_fail/06-select.rs");
+    t.compile_fail("tests/ui/test_fail/07-amphi_option_join.rs");
+    t.compile_fail("tests/ui/test_fail/08-replace_unused.rs");
+    t.compile_fail("tests/ui/test_fail/09-preset_tokio_unsupported.rs");
+    t.compile_fail("tests/ui/test_fail/10-preset_tokio_time.rs");
+    t.compile_fail("tests/ui/test_fail/11-preset_reqwest_stream.rs");
+    t.compile_fail("tests/ui/test_fail/12-unknown_tag.rs");
+    t.compile_fail("tests/ui/test_fail/13-tag_operand.rs");
+    t.compile_fail("tests/ui/test_fail/14-stream_endless.rs");
+    t.compile_fail("tests/ui/test_fail/15-preset_tokio_handle.rs");
+    t.compile_fail("tests/ui/test_fail/16-preset_embedded_hal_std.rs");
 }
@@ -1,5 +1,3 @@
-#![allow(redundant_semicolons)]
-
 use amphi::amphi;
 
 #[amphi]
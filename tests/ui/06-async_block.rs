@@ -0,0 +1,86 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use std::num::ParseIntError;
+
+    // `?` inside an async block only exits the async block
+    pub async fn double(input: &str) -> Option<i32> {
+        let parsed: Result<i32, ParseIntError> = async { Ok(input.parse::<i32>()? * 2) }.await;
+        parsed.ok()
+    }
+
+    // `?` inside macro arguments is seen too
+    pub async fn formatted(input: &str) -> Option<String> {
+        let formatted: Result<String, ParseIntError> =
+            async { Ok(format!("{}", input.parse::<i32>()?)) }.await;
+        formatted.ok()
+    }
+
+    // `return` inside an async block only exits the async block
+    pub async fn early_return(flag: bool) -> i32 {
+        let value = async {
+            if flag {
+                return 1;
+            }
+            2
+        }
+        .await;
+        value * 10
+    }
+
+    // `async move` takes ownership of the captured values, and copies the
+    // `Copy` ones
+    pub async fn moved() -> (String, u32, u32) {
+        let name = String::from("amphi");
+        let mut count = 1;
+        let (name, inner) = async move {
+            count += 1;
+            (name, count)
+        }
+        .await;
+        (name, count, inner)
+    }
+
+    // blocks without `?`, `return` or `move` are inlined
+    pub async fn inlined() -> i32 {
+        let a = async { 1 }.await;
+        let b = async {
+            let x = 2;
+            x * 2
+        }
+        .await;
+        a + b
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{double, early_return, formatted, inlined, moved};
+
+        assert_eq!(double("21"), Some(42));
+        assert_eq!(double("x"), None);
+        assert_eq!(formatted("7"), Some("7".to_string()));
+        assert_eq!(formatted("x"), None);
+        assert_eq!(early_return(true), 10);
+        assert_eq!(early_return(false), 20);
+        assert_eq!(moved(), ("amphi".to_string(), 1, 2));
+        assert_eq!(inlined(), 5);
+    }
+
+    // async
+    {
+        use self::asynchronous::{double, early_return, formatted, inlined, moved};
+
+        assert_eq!(double("21").await, Some(42));
+        assert_eq!(double("x").await, None);
+        assert_eq!(formatted("7").await, Some("7".to_string()));
+        assert_eq!(formatted("x").await, None);
+        assert_eq!(early_return(true).await, 10);
+        assert_eq!(early_return(false).await, 20);
+        assert_eq!(moved().await, ("amphi".to_string(), 1, 2));
+        assert_eq!(inlined().await, 5);
+    }
+}
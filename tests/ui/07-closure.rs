@@ -0,0 +1,60 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use std::num::ParseIntError;
+
+    pub async fn async_closure(values: Vec<i32>) -> Vec<i32> {
+        let offset = 1;
+        let add = async move |x: i32| x + offset;
+        let mut out = Vec::new();
+        for value in values {
+            out.push(add(value).await);
+        }
+        out
+    }
+
+    pub async fn closure_returning_async_block() -> String {
+        let name = String::from("amphi");
+        let greet = || async move { format!("hello {}", name) };
+        greet().await
+    }
+
+    pub async fn try_in_closure(input: &str) -> Result<i32, ParseIntError> {
+        let parse = |s: &str| {
+            let s = s.to_string();
+            async move {
+                let value: i32 = s.parse()?;
+                Ok::<_, ParseIntError>(value + 1)
+            }
+        };
+        let direct = |s: String| async move {
+            let value: i32 = s.parse()?;
+            Ok::<_, ParseIntError>(value * 2)
+        };
+        Ok(parse(input).await? + direct(input.to_string()).await?)
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{async_closure, closure_returning_async_block, try_in_closure};
+
+        assert_eq!(async_closure(vec![1, 2]), vec![2, 3]);
+        assert_eq!(closure_returning_async_block(), "hello amphi");
+        assert_eq!(try_in_closure("2"), Ok(7));
+        assert!(try_in_closure("x").is_err());
+    }
+
+    // async
+    {
+        use self::asynchronous::{async_closure, closure_returning_async_block, try_in_closure};
+
+        assert_eq!(async_closure(vec![1, 2]).await, vec![2, 3]);
+        assert_eq!(closure_returning_async_block().await, "hello amphi");
+        assert_eq!(try_in_closure("2").await, Ok(7));
+        assert!(try_in_closure("x").await.is_err());
+    }
+}
@@ -0,0 +1,98 @@
+#![allow(unused_imports)]
+
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    use futures::future::{BoxFuture, FutureExt};
+
+    pub struct Client {
+        pub base: u32,
+    }
+
+    impl Client {
+        pub fn impl_future(&self, n: u32) -> impl Future<Output = u32> + Send + '_ {
+            async move { self.base + n }
+        }
+
+        pub fn pinned(&self) -> Pin<Box<dyn Future<Output = Result<u32, String>> + Send + '_>> {
+            Box::pin(async move {
+                if self.base == 0 {
+                    return Err("zero".to_string());
+                }
+                Ok(self.base)
+            })
+        }
+
+        pub fn box_future(&self) -> BoxFuture<'_, Option<u32>> {
+            async move {
+                let value = self.pinned().await.ok()?;
+                Some(value * 2)
+            }
+            .boxed()
+        }
+    }
+
+    pub fn pinned_call(n: u32) -> Pin<Box<dyn Future<Output = u32> + Send>> {
+        Box::pin(plus_one(n))
+    }
+
+    pub fn boxed_call(n: u32) -> BoxFuture<'static, u32> {
+        plus_one(n).boxed()
+    }
+
+    pub async fn call_with<F>(f: F) -> u32
+    where
+        F: FnOnce(u32) -> BoxFuture<'static, u32>,
+    {
+        f(1).await + 1
+    }
+
+    pub async fn accept_pinned(fut: Pin<Box<dyn Future<Output = u32> + Send>>) -> u32 {
+        fut.await * 3
+    }
+
+    pub async fn plus_one(n: u32) -> u32 {
+        n + 1
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{accept_pinned, boxed_call, call_with, pinned_call, plus_one, Client};
+
+        let client = Client { base: 1 };
+        assert_eq!(client.impl_future(2), 3);
+        assert_eq!(client.pinned(), Ok(1));
+        assert_eq!(client.box_future(), Some(2));
+        assert_eq!(Client { base: 0 }.box_future(), None);
+        assert_eq!(call_with(|n| n * 10), 11);
+        assert_eq!(accept_pinned(plus_one(1)), 6);
+        assert_eq!(pinned_call(1), 2);
+        assert_eq!(boxed_call(2), 3);
+    }
+
+    // async
+    {
+        use futures::future::FutureExt;
+
+        use self::asynchronous::{
+            accept_pinned, boxed_call, call_with, pinned_call, plus_one, Client,
+        };
+
+        let client = Client { base: 1 };
+        assert_eq!(client.impl_future(2).await, 3);
+        assert_eq!(client.pinned().await, Ok(1));
+        assert_eq!(client.box_future().await, Some(2));
+        assert_eq!(Client { base: 0 }.box_future().await, None);
+        assert_eq!(call_with(|n| async move { n * 10 }.boxed()).await, 11);
+        assert_eq!(accept_pinned(Box::pin(plus_one(1))).await, 6);
+        assert_eq!(pinned_call(1).await, 2);
+        assert_eq!(boxed_call(2).await, 3);
+    }
+}
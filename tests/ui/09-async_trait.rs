@@ -0,0 +1,71 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use async_trait::async_trait;
+
+    #[async_trait]
+    pub trait Greeter {
+        async fn greet(&self) -> String;
+
+        async fn greet_twice(&self) -> String {
+            let first = self.greet().await;
+            let second = self.greet().await;
+            format!("{} {}", first, second)
+        }
+    }
+
+    #[async_trait::async_trait(?Send)]
+    pub trait LocalGreeter {
+        async fn local_greet(&self) -> String;
+    }
+
+    pub struct English;
+
+    #[async_trait]
+    impl Greeter for English {
+        async fn greet(&self) -> String {
+            "hello".to_string()
+        }
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl LocalGreeter for English {
+        async fn local_greet(&self) -> String {
+            "hi".to_string()
+        }
+    }
+
+    pub async fn greet_all(greeters: &[Box<dyn Greeter + Send + Sync>]) -> Vec<String> {
+        let mut out = Vec::new();
+        for greeter in greeters {
+            out.push(greeter.greet_twice().await);
+        }
+        out
+    }
+
+    pub async fn greet_local(greeter: &dyn LocalGreeter) -> String {
+        greeter.local_greet().await
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{greet_all, greet_local, English, Greeter};
+
+        let greeters: Vec<Box<dyn Greeter + Send + Sync>> = vec![Box::new(English)];
+        assert_eq!(greet_all(&greeters), vec!["hello hello".to_string()]);
+        assert_eq!(greet_local(&English), "hi");
+    }
+
+    // async
+    {
+        use self::asynchronous::{greet_all, greet_local, English, Greeter};
+
+        let greeters: Vec<Box<dyn Greeter + Send + Sync>> = vec![Box::new(English)];
+        assert_eq!(greet_all(&greeters).await, vec!["hello hello".to_string()]);
+        assert_eq!(greet_local(&English).await, "hi");
+    }
+}
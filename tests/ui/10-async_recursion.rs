@@ -0,0 +1,81 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use async_recursion::async_recursion;
+    use futures::future::{BoxFuture, FutureExt};
+
+    #[async_recursion]
+    pub async fn fib(n: u64) -> u64 {
+        if n < 2 {
+            return n;
+        }
+        fib(n - 1).await + fib(n - 2).await
+    }
+
+    pub struct Tree {
+        pub value: u64,
+        pub children: Vec<Tree>,
+    }
+
+    impl Tree {
+        #[async_recursion::async_recursion(?Send)]
+        pub async fn sum(&self) -> u64 {
+            let mut sum = self.value;
+            for child in &self.children {
+                sum += child.sum().await;
+            }
+            sum
+        }
+
+        pub fn depth(&self) -> BoxFuture<'_, usize> {
+            async move {
+                let mut depth = 0;
+                for child in &self.children {
+                    depth = depth.max(Box::pin(child.depth()).await);
+                }
+                depth + 1
+            }
+            .boxed()
+        }
+    }
+
+    pub async fn countdown(n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            1 + Box::pin(countdown(n - 1)).await
+        }
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{countdown, fib, Tree};
+
+        let tree = Tree {
+            value: 1,
+            children: vec![Tree { value: 2, children: vec![] }],
+        };
+        assert_eq!(fib(10), 55);
+        assert_eq!(tree.sum(), 3);
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(countdown(5), 5);
+    }
+
+    // async
+    {
+        use self::asynchronous::{countdown, fib, Tree};
+
+        let tree = Tree {
+            value: 1,
+            children: vec![Tree { value: 2, children: vec![] }],
+        };
+        assert_eq!(fib(10).await, 55);
+        assert_eq!(tree.sum().await, 3);
+        assert_eq!(tree.depth().await, 2);
+        assert_eq!(countdown(5).await, 5);
+    }
+}
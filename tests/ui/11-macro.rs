@@ -0,0 +1,72 @@
+use amphi::amphi;
+
+macro_rules! add {
+    ($a:expr, $b:expr) => {
+        $a + $b
+    };
+}
+
+#[amphi(macros(add), macro_rules)]
+mod amphi {
+    use std::fmt::Write;
+
+    macro_rules! twice {
+        ($e:expr) => {
+            $e.await + $e.await
+        };
+    }
+
+    pub async fn one() -> u32 {
+        1
+    }
+
+    pub async fn std_macros() -> String {
+        assert_eq!(one().await, 1);
+        assert!(one().await > 0, "{}", one().await);
+        let list = vec![one().await, one().await + 1];
+        let repeated = vec![one().await; 2];
+        let matched = matches!(Some(one().await), Some(1..=3));
+        let mut out = format!("{:?} {:?} {} {value}", list, repeated, matched, value = one().await);
+        write!(out, " {}", one().await).unwrap();
+        out
+    }
+
+    pub async fn user_macro() -> u32 {
+        add!(one().await, twice!(one()))
+    }
+
+    pub async fn tagged_in_macro() -> u32 {
+        let value = add!(
+            {
+                #[amphi(blocking)]
+                let x = 10;
+                #[amphi(asynchronous)]
+                let x = 20;
+                x
+            },
+            one().await
+        );
+        value
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{std_macros, tagged_in_macro, user_macro};
+
+        assert_eq!(std_macros(), "[1, 2] [1, 1] true 1 1");
+        assert_eq!(user_macro(), 3);
+        assert_eq!(tagged_in_macro(), 11);
+    }
+
+    // async
+    {
+        use self::asynchronous::{std_macros, tagged_in_macro, user_macro};
+
+        assert_eq!(std_macros().await, "[1, 2] [1, 1] true 1 1");
+        assert_eq!(user_macro().await, 3);
+        assert_eq!(tagged_in_macro().await, 21);
+    }
+}
@@ -0,0 +1,149 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+
+    pub fn numbers(n: u32) -> impl Stream<Item = u32> {
+        stream::iter(0..n)
+    }
+
+    pub fn doubled(n: u32) -> BoxStream<'static, u32> {
+        numbers(n).then(|x| async move { x * 2 }).boxed()
+    }
+
+    pub fn incremented(n: u32) -> BoxStream<'static, u32> {
+        numbers(n).map(|x| x + 1).boxed()
+    }
+
+    pub fn pinned(n: u32) -> std::pin::Pin<Box<dyn Stream<Item = u32> + Send>> {
+        Box::pin(numbers(n))
+    }
+
+    pub fn pages(last: u32) -> impl Stream<Item = Vec<u32>> {
+        stream::unfold(0, move |page| async move {
+            if page > last {
+                return None;
+            }
+            Some((vec![page; page as usize], page + 1))
+        })
+    }
+
+    pub fn generated(n: u32) -> impl Stream<Item = u32> {
+        async_stream::stream! {
+            for i in 0..n {
+                yield i + 10;
+            }
+        }
+    }
+
+    pub fn until_zero(values: Vec<u32>) -> impl Stream<Item = u32> {
+        async_stream::stream! {
+            for value in values {
+                if value == 0 {
+                    return;
+                }
+                yield value;
+            }
+        }
+    }
+
+    pub fn checked(n: u32) -> impl Stream<Item = Result<u32, String>> {
+        async_stream::try_stream! {
+            for i in 0..n {
+                if i == 2 {
+                    Err(format!("stop at {}", i))?;
+                }
+                yield i;
+            }
+        }
+    }
+
+    pub async fn sum<S>(mut stream: S) -> u32
+    where
+        S: Stream<Item = u32> + Unpin,
+    {
+        let mut total = 0;
+        while let Some(x) = stream.next().await {
+            total += x;
+        }
+        total
+    }
+
+    pub async fn first_error(n: u32) -> Result<Vec<u32>, String> {
+        checked(n).try_collect::<Vec<_>>().await
+    }
+
+    pub async fn first_ok() -> Result<Option<u32>, String> {
+        let mut stream = stream::iter(vec![Ok(7), Err("late".to_string())]);
+        let first = stream.try_next().await?;
+        Ok(first)
+    }
+
+    pub async fn all_pages() -> Vec<Vec<u32>> {
+        pages(2).collect().await
+    }
+
+    pub async fn buffered(n: u32) -> Vec<u32> {
+        numbers(n)
+            .map(|x| async move { x + 1 })
+            .buffered(2)
+            .collect()
+            .await
+    }
+
+    pub async fn generated_sum(n: u32) -> u32 {
+        let mut total = 0;
+        generated(n)
+            .for_each_concurrent(4, |x| {
+                total += x;
+                async {}
+            })
+            .await;
+        total
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(sum(numbers(4)), 6);
+        assert_eq!(sum(doubled(4)), 12);
+        assert_eq!(sum(incremented(3)), 6);
+        assert_eq!(sum(pinned(3)), 3);
+        assert_eq!(all_pages(), vec![vec![], vec![1], vec![2, 2]]);
+        assert_eq!(generated(3).collect::<Vec<_>>(), vec![10, 11, 12]);
+        assert_eq!(until_zero(vec![1, 2, 0, 3]).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(first_error(2), Ok(vec![0, 1]));
+        assert_eq!(first_error(5), Err("stop at 2".to_string()));
+        assert_eq!(first_ok(), Ok(Some(7)));
+        assert_eq!(buffered(3), vec![1, 2, 3]);
+        assert_eq!(generated_sum(2), 21);
+    }
+
+    // async
+    {
+        use futures::stream::StreamExt;
+
+        use self::asynchronous::*;
+
+        assert_eq!(sum(numbers(4)).await, 6);
+        assert_eq!(sum(doubled(4)).await, 12);
+        assert_eq!(sum(incremented(3)).await, 6);
+        assert_eq!(sum(pinned(3)).await, 3);
+        assert_eq!(all_pages().await, vec![vec![], vec![1], vec![2, 2]]);
+        assert_eq!(generated(3).collect::<Vec<_>>().await, vec![10, 11, 12]);
+        assert_eq!(
+            until_zero(vec![1, 2, 0, 3]).collect::<Vec<_>>().await,
+            vec![1, 2]
+        );
+        assert_eq!(first_error(2).await, Ok(vec![0, 1]));
+        assert_eq!(first_error(5).await, Err("stop at 2".to_string()));
+        assert_eq!(first_ok().await, Ok(Some(7)));
+        assert_eq!(buffered(3).await, vec![1, 2, 3]);
+        assert_eq!(generated_sum(2).await, 21);
+    }
+}
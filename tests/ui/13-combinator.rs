@@ -0,0 +1,111 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use futures::future::{self, join_all, ready, try_join_all};
+    use futures::{join, pin_mut, try_join};
+
+    pub async fn double(n: u32) -> u32 {
+        n * 2
+    }
+
+    pub async fn checked(n: u32) -> Result<u32, String> {
+        if n == 0 {
+            return Err("zero".to_string());
+        }
+        Ok(n)
+    }
+
+    pub async fn both() -> (u32, u32) {
+        join!(double(1), async { 3 })
+    }
+
+    pub async fn joined() -> (u32, u32, u32) {
+        future::join3(double(1), double(2), future::ready(5)).await
+    }
+
+    pub async fn imported() -> (u32, u32) {
+        use futures::future::join as join_pair;
+
+        join_pair(ready(1), double(2)).await
+    }
+
+    pub async fn try_both(a: u32, b: u32) -> Result<u32, String> {
+        let (a, b) = try_join!(checked(a), checked(b))?;
+        Ok(a + b)
+    }
+
+    pub async fn all(n: u32) -> Vec<u32> {
+        join_all((0..n).map(double)).await
+    }
+
+    pub async fn try_all(values: Vec<u32>) -> Result<Vec<u32>, String> {
+        try_join_all(values.into_iter().map(checked)).await
+    }
+
+    pub async fn pinned(n: u32) -> u32 {
+        let fut = double(n);
+        pin_mut!(fut);
+        let other = std::pin::pin!(double(1));
+        fut.await + other.await
+    }
+
+    pub async fn ok_or_err(n: u32) -> Result<u32, String> {
+        let res: Result<u32, String> = if n > 0 {
+            future::ok(n).await
+        } else {
+            future::err("zero".to_string()).await
+        };
+        res
+    }
+
+    pub mod local {
+        // not the `ready` imported by the parent module
+        fn ready(n: u32) -> u32 {
+            n + 1
+        }
+
+        pub async fn next(n: u32) -> u32 {
+            ready(n)
+        }
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(both(), (2, 3));
+        assert_eq!(joined(), (2, 4, 5));
+        assert_eq!(imported(), (1, 4));
+        assert_eq!(try_both(1, 2), Ok(3));
+        assert_eq!(try_both(0, 2), Err("zero".to_string()));
+        assert_eq!(all(3), vec![0, 2, 4]);
+        assert_eq!(try_all(vec![1, 2]), Ok(vec![1, 2]));
+        assert_eq!(try_all(vec![1, 0]), Err("zero".to_string()));
+        assert_eq!(pinned(2), 6);
+        assert_eq!(ok_or_err(1), Ok(1));
+        assert_eq!(ok_or_err(0), Err("zero".to_string()));
+        assert_eq!(local::next(1), 2);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(both().await, (2, 3));
+        assert_eq!(joined().await, (2, 4, 5));
+        assert_eq!(imported().await, (1, 4));
+        assert_eq!(try_both(1, 2).await, Ok(3));
+        assert_eq!(try_both(0, 2).await, Err("zero".to_string()));
+        assert_eq!(all(3).await, vec![0, 2, 4]);
+        assert_eq!(try_all(vec![1, 2]).await, Ok(vec![1, 2]));
+        assert_eq!(try_all(vec![1, 0]).await, Err("zero".to_string()));
+        assert_eq!(pinned(2).await, 6);
+        assert_eq!(ok_or_err(1).await, Ok(1));
+        assert_eq!(ok_or_err(0).await, Err("zero".to_string()));
+        assert_eq!(local::next(1).await, 2);
+    }
+}
@@ -0,0 +1,94 @@
+use amphi::amphi;
+
+#[amphi(join = "thread")]
+mod amphi {
+    use std::thread::{self, ThreadId};
+
+    use futures::future::{join_all, try_join_all};
+    use futures::{join, try_join};
+
+    pub struct Client {
+        pub base: u32,
+    }
+
+    impl Client {
+        pub async fn fetch(&self, n: u32) -> (u32, ThreadId) {
+            (self.base + n, thread::current().id())
+        }
+
+        pub async fn checked(&self, n: u32) -> Result<u32, String> {
+            if n == 0 {
+                return Err("zero".to_string());
+            }
+            Ok(self.base + n)
+        }
+
+        pub async fn both(&self) -> ((u32, ThreadId), (u32, ThreadId)) {
+            let name = "two".to_string();
+            join!(
+                self.fetch(1),
+                async move { self.fetch(name.len() as u32).await }
+            )
+        }
+
+        pub async fn try_both(&self, a: u32, b: u32) -> Result<(u32, u32), String> {
+            try_join!(self.checked(a), self.checked(b))
+        }
+
+        pub async fn all(&self, n: u32) -> Vec<(u32, ThreadId)> {
+            join_all((0..n).map(|i| self.fetch(i))).await
+        }
+
+        pub async fn try_all(&self, values: Vec<u32>) -> Result<Vec<u32>, String> {
+            try_join_all(values.into_iter().map(|n| self.checked(n))).await
+        }
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    let main_thread = std::thread::current().id();
+
+    // sync
+    {
+        use self::blocking::Client;
+
+        let client = Client { base: 10 };
+        let ((a, a_thread), (b, b_thread)) = client.both();
+        assert_eq!((a, b), (11, 13));
+        assert_ne!(a_thread, main_thread);
+        assert_ne!(b_thread, main_thread);
+        assert_ne!(a_thread, b_thread);
+
+        assert_eq!(client.try_both(1, 2), Ok((11, 12)));
+        assert_eq!(client.try_both(1, 0), Err("zero".to_string()));
+
+        let all = client.all(3);
+        assert_eq!(
+            all.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            vec![10, 11, 12]
+        );
+        assert!(all.iter().all(|(_, thread)| *thread != main_thread));
+
+        assert_eq!(client.try_all(vec![1, 2]), Ok(vec![11, 12]));
+        assert_eq!(client.try_all(vec![0, 2]), Err("zero".to_string()));
+    }
+
+    // async
+    {
+        use self::asynchronous::Client;
+
+        let client = Client { base: 10 };
+        let ((a, _), (b, _)) = client.both().await;
+        assert_eq!((a, b), (11, 13));
+        assert_eq!(client.try_both(1, 2).await, Ok((11, 12)));
+        assert_eq!(client.try_both(1, 0).await, Err("zero".to_string()));
+        let all = client.all(3).await;
+        assert_eq!(
+            all.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            vec![10, 11, 12]
+        );
+        assert_eq!(client.try_all(vec![1, 2]).await, Ok(vec![11, 12]));
+        assert_eq!(client.try_all(vec![0, 2]).await, Err("zero".to_string()));
+    }
+}
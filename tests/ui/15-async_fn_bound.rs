@@ -0,0 +1,83 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use std::future::Future;
+
+    pub async fn call<F: AsyncFn(u32) -> u32>(f: F) -> u32 {
+        f(1).await + f(2).await
+    }
+
+    pub async fn call_mut<F>(mut f: F) -> u32
+    where
+        F: AsyncFnMut() -> u32,
+    {
+        f().await;
+        f().await
+    }
+
+    pub async fn call_once(f: impl AsyncFnOnce() -> String) -> String {
+        f().await
+    }
+
+    pub async fn with_future<F, Fut: Future<Output = u32>>(f: F) -> u32
+    where
+        F: Fn(u32) -> Fut,
+    {
+        f(3).await * 2
+    }
+
+    pub async fn with_future_where<F, Fut, T>(f: F, value: T) -> Vec<T>
+    where
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = Vec<T>> + Send,
+        T: Clone,
+    {
+        f(value).await
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(call(|n| n * 10), 30);
+        let mut count = 0;
+        assert_eq!(
+            call_mut(|| {
+                count += 1;
+                count
+            }),
+            2
+        );
+        let name = "once".to_string();
+        assert_eq!(call_once(move || name), "once");
+        assert_eq!(with_future(|n| n + 1), 8);
+        assert_eq!(with_future_where(|n| vec![n; 2], 'a'), vec!['a', 'a']);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(call(async |n| n * 10).await, 30);
+        let mut count = 0;
+        assert_eq!(
+            call_mut(async || {
+                count += 1;
+                count
+            })
+            .await,
+            2
+        );
+        let name = "once".to_string();
+        assert_eq!(call_once(async move || name).await, "once");
+        assert_eq!(with_future(|n| async move { n + 1 }).await, 8);
+        assert_eq!(
+            with_future_where(|n| async move { vec![n; 2] }, 'a').await,
+            vec!['a', 'a']
+        );
+    }
+}
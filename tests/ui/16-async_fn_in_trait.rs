@@ -0,0 +1,82 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use std::future::Future;
+
+    pub trait Store {
+        async fn get(&self, key: u32) -> Option<String>;
+
+        fn len(&self) -> impl Future<Output = usize> + Send;
+
+        fn is_empty(&self) -> impl Future<Output = bool> {
+            async move { self.len().await == 0 }
+        }
+
+        async fn get_or(&self, key: u32, default: &str) -> String {
+            self.get(key).await.unwrap_or_else(|| default.to_string())
+        }
+    }
+
+    #[trait_variant::make(Source: Send)]
+    pub trait LocalSource {
+        async fn fetch(&self) -> u32;
+    }
+
+    pub struct Memory {
+        pub values: Vec<String>,
+    }
+
+    impl Store for Memory {
+        async fn get(&self, key: u32) -> Option<String> {
+            self.values.get(key as usize).cloned()
+        }
+
+        fn len(&self) -> impl Future<Output = usize> + Send {
+            async move { self.values.len() }
+        }
+    }
+
+    impl Source for Memory {
+        async fn fetch(&self) -> u32 {
+            self.values.len() as u32 * 10
+        }
+    }
+
+    pub async fn fetch_twice<S: Source + Sync>(source: &S) -> u32 {
+        source.fetch().await + source.fetch().await
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::{fetch_twice, LocalSource, Memory, Store};
+
+        let memory = Memory {
+            values: vec!["a".to_string()],
+        };
+        assert_eq!(memory.get(0), Some("a".to_string()));
+        assert_eq!(memory.len(), 1);
+        assert!(!memory.is_empty());
+        assert_eq!(memory.get_or(1, "b"), "b");
+        assert_eq!(memory.fetch(), 10);
+        assert_eq!(fetch_twice(&memory), 20);
+    }
+
+    // async
+    {
+        use self::asynchronous::{fetch_twice, LocalSource, Memory, Store};
+
+        let memory = Memory {
+            values: vec!["a".to_string()],
+        };
+        assert_eq!(memory.get(0).await, Some("a".to_string()));
+        assert_eq!(memory.len().await, 1);
+        assert!(!memory.is_empty().await);
+        assert_eq!(memory.get_or(1, "b").await, "b");
+        assert_eq!(memory.fetch().await, 10);
+        assert_eq!(fetch_twice(&memory).await, 20);
+    }
+}
@@ -0,0 +1,105 @@
+mod net {
+    pub mod nonblocking {
+        pub struct Client {
+            pub base: u32,
+        }
+
+        impl Client {
+            pub fn new(base: u32) -> Self {
+                Client { base }
+            }
+
+            pub async fn get(&self, n: u32) -> u32 {
+                self.base + n
+            }
+        }
+
+        pub trait AsyncSource {
+            async fn read(&self) -> u32;
+        }
+
+        impl AsyncSource for Client {
+            async fn read(&self) -> u32 {
+                self.base
+            }
+        }
+    }
+
+    pub mod blocking {
+        pub struct Client {
+            pub base: u32,
+        }
+
+        impl Client {
+            pub fn new(base: u32) -> Self {
+                Client { base }
+            }
+
+            pub fn get(&self, n: u32) -> u32 {
+                self.base + n * 100
+            }
+        }
+
+        pub trait Source {
+            fn read(&self) -> u32;
+        }
+
+        impl Source for Client {
+            fn read(&self) -> u32 {
+                self.base * 100
+            }
+        }
+    }
+}
+
+use amphi::amphi;
+
+#[amphi(replace(
+    crate::net::nonblocking => crate::net::blocking,
+    crate::net::nonblocking::AsyncSource => crate::net::blocking::Source,
+))]
+mod amphi {
+    use crate::net::nonblocking::{AsyncSource, Client};
+
+    pub async fn get(client: &Client) -> u32 {
+        client.get(1).await
+    }
+
+    pub async fn read<S: AsyncSource>(source: &S) -> u32 {
+        source.read().await
+    }
+
+    pub async fn new_client(base: u32) -> crate::net::nonblocking::Client {
+        crate::net::nonblocking::Client::new(base)
+    }
+
+    pub async fn read_qualified<S>(source: &S) -> u32
+    where
+        S: crate::net::nonblocking::AsyncSource,
+    {
+        <S as crate::net::nonblocking::AsyncSource>::read(source).await
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        let client = new_client(2);
+        assert_eq!(get(&client), 102);
+        assert_eq!(read(&client), 200);
+        assert_eq!(read_qualified(&client), 200);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let client = new_client(2).await;
+        assert_eq!(get(&client).await, 3);
+        assert_eq!(read(&client).await, 2);
+        assert_eq!(read_qualified(&client).await, 2);
+    }
+}
@@ -0,0 +1,74 @@
+use amphi::amphi;
+
+#[amphi(preset = "tokio")]
+mod amphi {
+    use std::path::Path;
+
+    use tokio::fs::{self, File};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    pub async fn write_lines(path: &Path, lines: &[&str]) -> tokio::io::Result<()> {
+        let mut file = File::create(path).await?;
+        for line in lines {
+            file.write_all(line.as_bytes()).await?;
+            file.write_all(b"\n").await?;
+        }
+        file.flush().await
+    }
+
+    pub async fn count_lines(path: &Path) -> std::io::Result<usize> {
+        let file = File::open(path).await?;
+        let mut lines = BufReader::new(file).lines();
+        let mut count = 0;
+        while let Some(_line) = lines.next_line().await? {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub async fn read_back(path: &Path) -> std::io::Result<String> {
+        let content = fs::read_to_string(path).await?;
+        fs::remove_file(path).await?;
+        Ok(content)
+    }
+
+    pub async fn echo(message: &[u8]) -> std::io::Result<Vec<u8>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let mut client = TcpStream::connect(addr).await?;
+        let (mut server, _) = listener.accept().await?;
+
+        client.write_all(message).await?;
+        let mut received = vec![0; message.len()];
+        server.read_exact(&mut received).await?;
+        Ok(received)
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let dir = std::env::temp_dir();
+
+    // sync
+    {
+        use self::blocking::*;
+
+        let path = dir.join("amphi-preset-tokio-blocking.txt");
+        write_lines(&path, &["a", "b", "c"]).unwrap();
+        assert_eq!(count_lines(&path).unwrap(), 3);
+        assert_eq!(read_back(&path).unwrap(), "a\nb\nc\n");
+        assert_eq!(echo(b"ping").unwrap(), b"ping");
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let path = dir.join("amphi-preset-tokio-async.txt");
+        write_lines(&path, &["a", "b", "c"]).await.unwrap();
+        assert_eq!(count_lines(&path).await.unwrap(), 3);
+        assert_eq!(read_back(&path).await.unwrap(), "a\nb\nc\n");
+        assert_eq!(echo(b"ping").await.unwrap(), b"ping");
+    }
+}
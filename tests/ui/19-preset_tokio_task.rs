@@ -0,0 +1,104 @@
+use amphi::amphi;
+
+#[amphi(preset = "tokio")]
+mod amphi {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+    use tokio::task;
+    use tokio::time::{sleep, Instant};
+
+    pub async fn count() -> usize {
+        let counter = Arc::new(Mutex::new(0));
+        let first = {
+            let counter = counter.clone();
+            tokio::spawn(async move { *counter.lock().await += 1 })
+        };
+        let second = {
+            let counter = counter.clone();
+            tokio::spawn(async move { *counter.lock().await += 2 })
+        };
+        first.await.unwrap();
+        second.await.unwrap();
+        let total = *counter.lock().await;
+        total
+    }
+
+    pub async fn square(value: u64) -> u64 {
+        let handle = tokio::spawn(async move { value * value });
+        handle.await.unwrap()
+    }
+
+    pub async fn sum(values: Vec<u64>) -> u64 {
+        let (tx, mut rx) = mpsc::channel(4);
+        let producer = task::spawn(async move {
+            for value in values {
+                tx.send(value).await.unwrap();
+            }
+        });
+        let mut sum = 0;
+        while let Some(value) = rx.recv().await {
+            sum += value;
+        }
+        producer.await.unwrap();
+        sum
+    }
+
+    pub async fn reply(value: u64) -> u64 {
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            tx.send(value + 1).unwrap();
+        })
+        .await
+        .unwrap();
+        rx.await.unwrap()
+    }
+
+    pub async fn heavy(value: u64) -> u64 {
+        task::spawn_blocking(move || value * 2).await.unwrap()
+    }
+
+    pub async fn shared(value: u64) -> u64 {
+        let lock = RwLock::new(value);
+        *lock.write().await += 1;
+        let read = *lock.read().await;
+        read
+    }
+
+    pub async fn nap(millis: u64) -> Duration {
+        let start = Instant::now();
+        sleep(Duration::from_millis(millis)).await;
+        tokio::task::yield_now().await;
+        start.elapsed()
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(count(), 3);
+        assert_eq!(square(3), 9);
+        assert_eq!(sum(vec![1, 2, 3, 4, 5, 6]), 21);
+        assert_eq!(reply(1), 2);
+        assert_eq!(heavy(21), 42);
+        assert_eq!(shared(1), 2);
+        assert!(nap(10) >= std::time::Duration::from_millis(10));
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(count().await, 3);
+        assert_eq!(square(3).await, 9);
+        assert_eq!(sum(vec![1, 2, 3, 4, 5, 6]).await, 21);
+        assert_eq!(reply(1).await, 2);
+        assert_eq!(heavy(21).await, 42);
+        assert_eq!(shared(1).await, 2);
+        assert!(nap(10).await >= std::time::Duration::from_millis(10));
+    }
+}
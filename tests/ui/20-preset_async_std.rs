@@ -0,0 +1,108 @@
+use amphi::amphi;
+
+#[amphi(preset = "async-std")]
+mod amphi {
+    use std::time::Duration;
+
+    use async_std::fs::File;
+    use async_std::io::{BufReader, ReadExt};
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::path::Path;
+    use async_std::prelude::*;
+    use async_std::sync::{Arc, Mutex};
+    use async_std::task;
+
+    pub async fn write_lines(path: &Path, lines: &[&str]) -> std::io::Result<()> {
+        let mut file = File::create(path).await?;
+        for line in lines {
+            file.write_all(line.as_bytes()).await?;
+            file.write_all(b"\n").await?;
+        }
+        file.flush().await
+    }
+
+    pub async fn read_lines(path: &Path) -> std::io::Result<Vec<String>> {
+        let file = File::open(path).await?;
+        let mut lines = vec![];
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        while reader.read_line(&mut line).await? > 0 {
+            lines.push(line.trim_end().to_string());
+            line.clear();
+        }
+        async_std::fs::remove_file(path).await?;
+        Ok(lines)
+    }
+
+    pub async fn read_all<R>(mut reader: R) -> std::io::Result<Vec<u8>>
+    where
+        R: futures::io::AsyncRead + Unpin,
+    {
+        let mut content = vec![];
+        futures::io::AsyncReadExt::read_to_end(&mut reader, &mut content).await?;
+        Ok(content)
+    }
+
+    pub async fn echo(message: &[u8]) -> std::io::Result<Vec<u8>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let mut client = TcpStream::connect(addr).await?;
+        let (mut server, _) = listener.accept().await?;
+
+        client.write_all(message).await?;
+        let mut received = vec![0; message.len()];
+        server.read_exact(&mut received).await?;
+        Ok(received)
+    }
+
+    pub async fn count() -> usize {
+        let counter = Arc::new(Mutex::new(0));
+        let handle = {
+            let counter = counter.clone();
+            task::spawn(async move {
+                task::sleep(Duration::from_millis(1)).await;
+                *counter.lock().await += 1;
+                1
+            })
+        };
+        let spawned = handle.await;
+        let blocking = task::spawn_blocking(|| 2).await;
+        *counter.lock().await += spawned + blocking;
+        let total = *counter.lock().await;
+        total
+    }
+
+    pub fn run() -> usize {
+        task::block_on(async { count().await })
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    let dir = std::env::temp_dir();
+
+    // sync
+    {
+        use self::blocking::*;
+
+        let path = dir.join("amphi-preset-async-std-blocking.txt");
+        write_lines(&path, &["a", "b"]).unwrap();
+        assert_eq!(read_lines(&path).unwrap(), ["a", "b"]);
+        assert_eq!(read_all(&b"abc"[..]).unwrap(), b"abc");
+        assert_eq!(echo(b"ping").unwrap(), b"ping");
+        assert_eq!(count(), 4);
+        assert_eq!(run(), 4);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let path = dir.join("amphi-preset-async-std-async.txt");
+        write_lines(path.as_ref(), &["a", "b"]).await.unwrap();
+        assert_eq!(read_lines(path.as_ref()).await.unwrap(), ["a", "b"]);
+        assert_eq!(read_all(&b"abc"[..]).await.unwrap(), b"abc");
+        assert_eq!(echo(b"ping").await.unwrap(), b"ping");
+        assert_eq!(count().await, 4);
+    }
+}
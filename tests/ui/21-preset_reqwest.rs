@@ -0,0 +1,95 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+
+use amphi::amphi;
+
+#[amphi(preset = "reqwest")]
+mod amphi {
+    use reqwest::{Client, RequestBuilder, Response};
+
+    pub struct Api {
+        client: Client,
+        base: String,
+    }
+
+    impl Api {
+        pub fn new(base: &str) -> reqwest::Result<Self> {
+            let client = reqwest::ClientBuilder::new().build()?;
+            Ok(Self {
+                client,
+                base: base.to_string(),
+            })
+        }
+
+        fn get(&self, path: &str) -> RequestBuilder {
+            self.client.get(format!("{}{}", self.base, path))
+        }
+
+        pub async fn text(&self, path: &str) -> reqwest::Result<String> {
+            self.get(path)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+        }
+
+        pub async fn status(&self, path: &str) -> reqwest::Result<u16> {
+            let response: Response = self.get(path).send().await?;
+            Ok(response.status().as_u16())
+        }
+    }
+
+    pub async fn fetch(url: &str) -> reqwest::Result<String> {
+        reqwest::get(url).await?.text().await
+    }
+}
+
+/// Answer `count` requests with `hello`, and return the address listened on.
+fn serve(count: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(count) {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                )
+                .unwrap();
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        let base = serve(3);
+        let api = Api::new(&base).unwrap();
+        assert_eq!(api.text("/").unwrap(), "hello");
+        assert_eq!(api.status("/").unwrap(), 200);
+        assert_eq!(fetch(&base).unwrap(), "hello");
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let base = serve(3);
+            let api = Api::new(&base).unwrap();
+            assert_eq!(api.text("/").await.unwrap(), "hello");
+            assert_eq!(api.status("/").await.unwrap(), 200);
+            assert_eq!(fetch(&base).await.unwrap(), "hello");
+        });
+    }
+}
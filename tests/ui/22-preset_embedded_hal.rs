@@ -0,0 +1,188 @@
+#![no_std]
+use amphi::amphi;
+
+#[amphi(preset = "embedded-hal")]
+mod amphi {
+    use embedded_hal_async::delay::DelayNs;
+    use embedded_hal_async::digital::Wait;
+    use embedded_hal_async::i2c::I2c;
+    use embedded_hal_async::spi::SpiDevice;
+
+    const ADDRESS: u8 = 0x48;
+
+    pub struct Sensor<I, D, P> {
+        i2c: I,
+        delay: D,
+        ready: P,
+    }
+
+    impl<I: I2c, D: DelayNs, P: Wait> Sensor<I, D, P> {
+        pub fn new(i2c: I, delay: D, ready: P) -> Self {
+            Self { i2c, delay, ready }
+        }
+
+        pub async fn read(&mut self) -> Result<u16, I::Error> {
+            self.i2c.write(ADDRESS, &[0x01]).await?;
+            self.delay.delay_ms(1).await;
+            let _ = self.ready.wait_for_high().await;
+            let mut buf = [0; 2];
+            self.i2c.write_read(ADDRESS, &[0x00], &mut buf).await?;
+            Ok(u16::from_be_bytes(buf))
+        }
+    }
+
+    pub async fn transfer<S: SpiDevice>(spi: &mut S, word: u8) -> Result<u8, S::Error> {
+        let mut buf = [word];
+        spi.transfer_in_place(&mut buf).await?;
+        Ok(buf[0])
+    }
+
+    pub async fn edges<P: Wait>(pin: &mut P) -> Result<(), P::Error> {
+        pin.wait_for_rising_edge().await?;
+        pin.wait_for_falling_edge().await?;
+        pin.wait_for_any_edge().await?;
+        pin.wait_for_low().await
+    }
+}
+
+fn main() {
+    // the conversion must not need std, only the mocks and the executor do
+    extern crate std;
+
+    use core::convert::Infallible;
+
+    use embedded_hal::{delay, digital, i2c, spi};
+
+    struct Bus;
+
+    impl i2c::ErrorType for Bus {
+        type Error = Infallible;
+    }
+
+    impl i2c::I2c for Bus {
+        fn transaction(
+            &mut self,
+            _address: u8,
+            operations: &mut [i2c::Operation<'_>],
+        ) -> Result<(), Infallible> {
+            for operation in operations {
+                if let i2c::Operation::Read(buf) = operation {
+                    buf.copy_from_slice(&[0x12, 0x34]);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl embedded_hal_async::i2c::I2c for Bus {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [i2c::Operation<'_>],
+        ) -> Result<(), Infallible> {
+            i2c::I2c::transaction(self, address, operations)
+        }
+    }
+
+    struct Delay;
+
+    impl delay::DelayNs for Delay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    impl embedded_hal_async::delay::DelayNs for Delay {
+        async fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    /// a pin toggling on each read
+    struct Pin(bool);
+
+    impl digital::ErrorType for Pin {
+        type Error = Infallible;
+    }
+
+    impl digital::InputPin for Pin {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            self.0 = !self.0;
+            Ok(self.0)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    impl embedded_hal_async::digital::Wait for Pin {
+        async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    /// a device incrementing each word
+    struct Device;
+
+    impl spi::ErrorType for Device {
+        type Error = Infallible;
+    }
+
+    impl spi::SpiDevice for Device {
+        fn transaction(
+            &mut self,
+            operations: &mut [spi::Operation<'_, u8>],
+        ) -> Result<(), Infallible> {
+            for operation in operations {
+                if let spi::Operation::TransferInPlace(buf) = operation {
+                    buf.iter_mut().for_each(|word| *word += 1);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl embedded_hal_async::spi::SpiDevice for Device {
+        async fn transaction(
+            &mut self,
+            operations: &mut [spi::Operation<'_, u8>],
+        ) -> Result<(), Infallible> {
+            spi::SpiDevice::transaction(self, operations)
+        }
+    }
+
+    // sync
+    {
+        use self::blocking::*;
+
+        let mut sensor = Sensor::new(Bus, Delay, Pin(false));
+        assert_eq!(sensor.read(), Ok(0x1234));
+        assert_eq!(transfer(&mut Device, 1), Ok(2));
+        assert_eq!(edges(&mut Pin(false)), Ok(()));
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        futures::executor::block_on(async {
+            let mut sensor = Sensor::new(Bus, Delay, Pin(false));
+            assert_eq!(sensor.read().await, Ok(0x1234));
+            assert_eq!(transfer(&mut Device, 1).await, Ok(2));
+            assert_eq!(edges(&mut Pin(false)).await, Ok(()));
+        });
+    }
+}
@@ -0,0 +1,74 @@
+use amphi::amphi;
+
+#[amphi(preset = "tokio", preset = "tungstenite")]
+mod amphi {
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::{Message, Result};
+    use tokio_tungstenite::{accept_async, MaybeTlsStream, WebSocketStream};
+
+    /// Echo the messages of one client until it closes.
+    pub async fn echo(listener: TcpListener) -> Result<()> {
+        let (stream, _) = listener.accept().await?;
+        let mut ws = accept_async(stream).await?;
+        while let Some(message) = ws.next().await {
+            let message = message?;
+            if message.is_close() {
+                break;
+            }
+            ws.send(message).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn connect(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let (ws, _) = tokio_tungstenite::connect_async(url).await?;
+        Ok(ws)
+    }
+
+    pub async fn ask(
+        ws: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+        text: &str,
+    ) -> Result<Option<String>> {
+        SinkExt::send(ws, Message::text(text)).await?;
+        match ws.next().await {
+            Some(message) => Ok(Some(message?.into_text()?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn close(mut ws: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<()> {
+        ws.close(None).await
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || echo(listener));
+        let mut ws = connect(&url).unwrap();
+        assert_eq!(ask(&mut ws, "ping").unwrap().as_deref(), Some("ping"));
+        assert_eq!(ask(&mut ws, "pong").unwrap().as_deref(), Some("pong"));
+        close(ws).unwrap();
+        server.join().unwrap().unwrap();
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(echo(listener));
+        let mut ws = connect(&url).await.unwrap();
+        assert_eq!(ask(&mut ws, "ping").await.unwrap().as_deref(), Some("ping"));
+        assert_eq!(ask(&mut ws, "pong").await.unwrap().as_deref(), Some("pong"));
+        close(ws).await.unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
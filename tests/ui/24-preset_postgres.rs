@@ -0,0 +1,81 @@
+use amphi::amphi;
+
+#[amphi(preset = "tokio", preset = "postgres")]
+mod amphi {
+    use tokio_postgres::{Client, Error, NoTls};
+
+    pub async fn connect(params: &str) -> Result<Client, Error> {
+        let (client, connection) = tokio_postgres::connect(params, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(error) = connection.await {
+                eprintln!("connection error: {}", error);
+            }
+        });
+        Ok(client)
+    }
+
+    pub async fn connect_with(config: &tokio_postgres::Config) -> Result<i32, Error> {
+        let (mut client, connection) = config.connect(NoTls).await?;
+        tokio::spawn(connection);
+        answer(&mut client).await
+    }
+
+    pub async fn answer(client: &mut Client) -> Result<i32, Error> {
+        let row = client.query_one("SELECT 42", &[]).await?;
+        Ok(row.get(0))
+    }
+
+    pub async fn sum(client: &mut Client, values: &[i32]) -> Result<i64, Error> {
+        let transaction = client.transaction().await?;
+        transaction
+            .execute("CREATE TEMPORARY TABLE amphi (value INT)", &[])
+            .await?;
+        for value in values {
+            transaction
+                .execute("INSERT INTO amphi VALUES ($1)", &[value])
+                .await?;
+        }
+        let row = transaction
+            .query_one("SELECT SUM(value) FROM amphi", &[])
+            .await?;
+        transaction.rollback().await?;
+        Ok(row.get(0))
+    }
+}
+
+// only run against a database given by `AMPHI_POSTGRES`, like
+// `host=localhost user=postgres`
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let params = match std::env::var("AMPHI_POSTGRES") {
+        Ok(params) => params,
+        Err(_) => return,
+    };
+
+    // sync
+    {
+        use self::blocking::*;
+
+        let params = params.clone();
+        std::thread::spawn(move || {
+            let mut client = connect(&params).unwrap();
+            assert_eq!(answer(&mut client).unwrap(), 42);
+            assert_eq!(sum(&mut client, &[1, 2, 3]).unwrap(), 6);
+            let config = params.parse().unwrap();
+            assert_eq!(connect_with(&config).unwrap(), 42);
+        })
+        .join()
+        .unwrap();
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let mut client = connect(&params).await.unwrap();
+        assert_eq!(answer(&mut client).await.unwrap(), 42);
+        assert_eq!(sum(&mut client, &[1, 2, 3]).await.unwrap(), 6);
+        let config = params.parse().unwrap();
+        assert_eq!(connect_with(&config).await.unwrap(), 42);
+    }
+}
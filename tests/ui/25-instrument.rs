@@ -0,0 +1,67 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use tracing::{info_span, Instrument, Span};
+
+    /// the name of the span entered
+    fn current() -> Option<&'static str> {
+        Span::current().metadata().map(|metadata| metadata.name())
+    }
+
+    async fn inner() -> Option<&'static str> {
+        current()
+    }
+
+    #[tracing::instrument]
+    pub async fn attributed(value: u32) -> Option<&'static str> {
+        current()
+    }
+
+    pub async fn instrumented() -> (Option<&'static str>, Option<&'static str>) {
+        let called = inner().instrument(info_span!("called")).await;
+        let block = async move { inner().await }
+            .instrument(info_span!("block"))
+            .await;
+        (called, block)
+    }
+
+    pub async fn nested() -> Result<Option<&'static str>, ()> {
+        let span = info_span!("outer");
+        let name = async {
+            let name = inner().in_current_span().await;
+            Ok::<_, ()>(name)
+        }
+        .instrument(span)
+        .await?;
+        Ok(name)
+    }
+}
+
+fn main() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_subscriber::filter::LevelFilter::TRACE);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(attributed(1), Some("attributed"));
+        assert_eq!(instrumented(), (Some("called"), Some("block")));
+        assert_eq!(nested(), Ok(Some("outer")));
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        futures::executor::block_on(async {
+            assert_eq!(attributed(1).await, Some("attributed"));
+            assert_eq!(instrumented().await, (Some("called"), Some("block")));
+            assert_eq!(nested().await, Ok(Some("outer")));
+        });
+    }
+}
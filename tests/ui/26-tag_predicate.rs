@@ -0,0 +1,82 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    #[amphi(not(blocking))]
+    pub fn version() -> &'static str {
+        "asynchronous"
+    }
+
+    #[amphi(not(asynchronous))]
+    pub fn version() -> &'static str {
+        "blocking"
+    }
+
+    #[amphi(any(blocking, asynchronous))]
+    pub fn both() -> bool {
+        true
+    }
+
+    #[amphi(all(blocking, not(asynchronous)))]
+    pub fn blocking_only() -> bool {
+        true
+    }
+
+    #[amphi(all(blocking, asynchronous))]
+    pub fn never() -> bool {
+        true
+    }
+
+    #[amphi(any())]
+    pub fn none() -> bool {
+        true
+    }
+
+    pub async fn tagged() -> Vec<&'static str> {
+        let mut tags = vec![];
+        #[amphi(not(blocking))]
+        {
+            tags.push("not(blocking)")
+        }
+        #[amphi(any(blocking))]
+        {
+            tags.push("any(blocking)")
+        }
+        #[amphi(all())]
+        let tag = "all()";
+        #[amphi(not(all()))]
+        let tag = "not(all())";
+        tags.push(tag);
+        #[amphi(not(blocking))]
+        {
+            tags.push("not(blocking) block");
+        }
+        tags
+    }
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(version(), "blocking");
+        assert!(both());
+        assert!(blocking_only());
+        assert_eq!(tagged(), ["any(blocking)", "all()"]);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(version(), "asynchronous");
+        assert!(both());
+        futures::executor::block_on(async {
+            assert_eq!(
+                tagged().await,
+                ["not(blocking)", "all()", "not(blocking) block"]
+            );
+        });
+    }
+}
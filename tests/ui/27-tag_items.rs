@@ -0,0 +1,115 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    #[amphi(asynchronous)]
+    use futures::io::AsyncReadExt;
+    #[amphi(blocking)]
+    use std::io::Read;
+
+    #[amphi(blocking)]
+    extern crate std as blocking_std;
+
+    #[amphi(blocking)]
+    pub const VERSION: &str = "blocking";
+    #[amphi(asynchronous)]
+    pub const VERSION: &str = "asynchronous";
+
+    #[amphi(blocking)]
+    pub static COUNT: usize = 1;
+    #[amphi(asynchronous)]
+    pub static COUNT: usize = 2;
+
+    #[amphi(blocking)]
+    pub type Bytes = Vec<u8>;
+    #[amphi(asynchronous)]
+    pub type Bytes = Box<[u8]>;
+
+    #[amphi(blocking)]
+    pub union Word {
+        pub int: u32,
+    }
+    #[amphi(asynchronous)]
+    pub union Word {
+        pub int: u32,
+        pub float: f32,
+    }
+
+    #[amphi(blocking)]
+    macro_rules! name {
+        () => {
+            "blocking"
+        };
+    }
+    #[amphi(asynchronous)]
+    macro_rules! name {
+        () => {
+            "asynchronous"
+        };
+    }
+
+    #[amphi(blocking)]
+    extern "C" {
+        fn abs(input: i32) -> i32;
+    }
+
+    pub fn name() -> &'static str {
+        name!()
+    }
+
+    #[amphi(blocking)]
+    pub fn absolute(value: i32) -> i32 {
+        let _ = blocking_std::mem::size_of::<Word>();
+        unsafe { abs(value) }
+    }
+
+    pub async fn read(mut bytes: &[u8]) -> Bytes {
+        let mut buf = vec![];
+        bytes.read_to_end(&mut buf).await.unwrap();
+        buf.into()
+    }
+
+    pub async fn nested() -> &'static str {
+        #[amphi(blocking)]
+        fn version() -> &'static str {
+            "blocking"
+        }
+        #[amphi(asynchronous)]
+        fn version() -> &'static str {
+            "asynchronous"
+        }
+        #[amphi(asynchronous)]
+        use futures::future::ready;
+        #[amphi(asynchronous)]
+        let () = ready(()).await;
+        version()
+    }
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!((VERSION, COUNT, name()), ("blocking", 1, "blocking"));
+        assert_eq!(absolute(-1), 1);
+        assert_eq!(unsafe { Word { int: 1 }.int }, 1);
+        assert_eq!(read(b"abc"), b"abc".to_vec());
+        assert_eq!(nested(), "blocking");
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(
+            (VERSION, COUNT, name()),
+            ("asynchronous", 2, "asynchronous")
+        );
+        assert_eq!(std::mem::size_of::<Word>(), 4);
+        futures::executor::block_on(async {
+            assert_eq!(read(b"abc").await, b"abc".to_vec().into_boxed_slice());
+            assert_eq!(nested().await, "asynchronous");
+        });
+    }
+}
@@ -0,0 +1,91 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    pub struct Connection {
+        pub open: bool,
+        #[amphi(asynchronous)]
+        pub waker: Option<std::task::Waker>,
+    }
+
+    pub struct Pair(pub u8, #[amphi(blocking)] pub u8);
+
+    pub enum Event {
+        Closed,
+        #[amphi(asynchronous)]
+        Pending,
+    }
+
+    pub trait Close {
+        #[amphi(blocking)]
+        fn close(self) -> bool;
+
+        #[amphi(asynchronous)]
+        async fn shutdown(self) -> bool;
+
+        #[amphi(blocking)]
+        const NAME: &'static str = "blocking";
+        #[amphi(asynchronous)]
+        const NAME: &'static str = "asynchronous";
+    }
+
+    impl Close for Connection {
+        #[amphi(blocking)]
+        fn close(self) -> bool {
+            self.open
+        }
+
+        #[amphi(asynchronous)]
+        async fn shutdown(self) -> bool {
+            self.open && self.waker.is_none()
+        }
+    }
+
+    pub fn describe(event: Event) -> &'static str {
+        match event {
+            Event::Closed => "closed",
+            #[amphi(asynchronous)]
+            Event::Pending => "pending",
+        }
+    }
+
+    pub fn sum<#[amphi(asynchronous)] T: Into<u8>>(
+        first: u8,
+        #[amphi(asynchronous)] second: T,
+    ) -> u8 {
+        #[amphi(asynchronous)]
+        let first = first + second.into();
+        first
+    }
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        let connection = Connection { open: true };
+        assert!(connection.close());
+        assert_eq!(<Connection as Close>::NAME, "blocking");
+        assert_eq!(Pair(1, 2).1, 2);
+        assert_eq!(describe(Event::Closed), "closed");
+        assert_eq!(sum(1), 1);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        let connection = Connection {
+            open: true,
+            waker: None,
+        };
+        assert_eq!(<Connection as Close>::NAME, "asynchronous");
+        assert_eq!(std::mem::size_of::<Pair>(), 1);
+        assert_eq!(describe(Event::Pending), "pending");
+        assert_eq!(sum(1, 2u8), 3);
+        futures::executor::block_on(async {
+            assert!(connection.shutdown().await);
+        });
+    }
+}
@@ -0,0 +1,77 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    // `all()` always holds
+    #[cfg_attr(all(), amphi(blocking))]
+    pub fn version() -> &'static str {
+        "blocking"
+    }
+
+    #[cfg_attr(all(), amphi(asynchronous))]
+    pub fn version() -> &'static str {
+        "asynchronous"
+    }
+
+    // `any()` never holds, so the tag does not apply
+    #[cfg_attr(any(), amphi(blocking))]
+    pub fn untagged() -> bool {
+        true
+    }
+
+    #[cfg_attr(all(), amphi(asynchronous), derive(Debug, PartialEq))]
+    pub struct Unit;
+
+    // attributes that are not a path are kept along the tag
+    #[cfg_attr(all(), unsafe(no_mangle), amphi(blocking))]
+    pub extern "C" fn amphi_unmangled() -> u32 {
+        1
+    }
+
+    #[amphi::amphi(blocking)]
+    pub fn qualified() -> bool {
+        true
+    }
+
+    pub async fn tagged() -> Vec<&'static str> {
+        let mut tags = vec![];
+        #[cfg_attr(all(), amphi(blocking))]
+        {
+            tags.push("cfg_attr(all())")
+        }
+        #[cfg_attr(any(), amphi(blocking))]
+        {
+            tags.push("cfg_attr(any())")
+        }
+        #[amphi::amphi(asynchronous)]
+        {
+            tags.push("amphi::amphi")
+        }
+        tags
+    }
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(version(), "blocking");
+        assert!(untagged());
+        assert!(qualified());
+        assert_eq!(amphi_unmangled(), 1);
+        assert_eq!(tagged(), ["cfg_attr(all())", "cfg_attr(any())"]);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(version(), "asynchronous");
+        assert!(untagged());
+        assert_eq!(Unit, Unit);
+        futures::executor::block_on(async {
+            assert_eq!(tagged().await, ["cfg_attr(any())", "amphi::amphi"]);
+        });
+    }
+}
@@ -0,0 +1,89 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    pub struct Point {
+        pub x: u8,
+        pub y: u8,
+    }
+
+    fn sum(values: &[u8]) -> u8 {
+        values.iter().sum()
+    }
+
+    fn add(value: u8, #[amphi(blocking)] other: u8) -> u8 {
+        #[amphi(blocking)]
+        let value = value + other;
+        value
+    }
+
+    pub async fn removed() -> (u8, usize, (u8,), u8, &'static str) {
+        let args = sum(&[
+            1,
+            #[amphi(blocking)]
+            2,
+            #[amphi(asynchronous)]
+            3,
+        ]);
+        let len = Vec::from([
+            1,
+            #[amphi(asynchronous)]
+            2,
+        ])
+        .len();
+        let tuple = (
+            #[amphi(blocking)]
+            1,
+            #[amphi(asynchronous)]
+            2,
+        );
+        let point = Point {
+            #[amphi(blocking)]
+            x: 1,
+            #[amphi(asynchronous)]
+            x: 2,
+            y: 3,
+        };
+        #[amphi(asynchronous)]
+        async {}.await;
+        let tail = {
+            #[amphi(blocking)]
+            {
+                "blocking"
+            }
+            #[amphi(asynchronous)]
+            {
+                "asynchronous"
+            }
+        };
+        (
+            add(
+                args,
+                #[amphi(blocking)]
+                1,
+            ),
+            len,
+            tuple,
+            point.x + point.y,
+            tail,
+        )
+    }
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(removed(), (4, 1, (1,), 4, "blocking"));
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        futures::executor::block_on(async {
+            assert_eq!(removed().await, (4, 2, (2,), 5, "asynchronous"));
+        });
+    }
+}
@@ -0,0 +1,51 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    #[amphi(blocking, attr(derive(Clone, Debug, PartialEq)))]
+    #[amphi(asynchronous, attr(derive(Default)))]
+    pub struct Client {
+        pub retries: u8,
+    }
+
+    #[cfg_attr(all(), amphi(asynchronous, attr(must_use, derive(Debug))))]
+    pub struct Builder {
+        #[amphi(blocking, attr(allow(dead_code)))]
+        timeout: u8,
+    }
+
+    impl Builder {
+        #[amphi(
+            not(blocking),
+            attr(must_use = "the builder does nothing unless built")
+        )]
+        pub fn new() -> Self {
+            Self { timeout: 0 }
+        }
+
+        pub async fn build(self) -> Client {
+            Client { retries: 3 }
+        }
+    }
+}
+
+fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        let client = Builder::new().build();
+        assert_eq!(client.clone(), Client { retries: 3 });
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(Client::default().retries, 0);
+        assert_eq!(format!("{:?}", Builder::new()), "Builder { timeout: 0 }");
+        futures::executor::block_on(async {
+            assert_eq!(Builder::new().build().await.retries, 3);
+        });
+    }
+}
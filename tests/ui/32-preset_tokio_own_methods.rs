@@ -0,0 +1,90 @@
+use amphi::amphi;
+
+#[amphi(preset = "tokio")]
+mod amphi {
+    use std::sync::Arc;
+
+    use tokio::sync::Mutex;
+
+    /// A type of its own whose methods share their name with tokio ones.
+    pub struct Source {
+        pub value: u8,
+    }
+
+    impl Source {
+        pub async fn recv(&mut self) -> u8 {
+            self.value += 1;
+            self.value
+        }
+
+        pub async fn lock(&self) -> u32 {
+            self.value as u32 * 10
+        }
+
+        pub async fn read(&self) -> u8 {
+            self.value
+        }
+
+        pub async fn read_u8(&self) -> u8 {
+            self.value
+        }
+
+        pub fn into_split(self) -> (u8, u8) {
+            (self.value / 2, self.value % 2)
+        }
+
+        pub async fn shutdown(self) -> u8 {
+            0
+        }
+    }
+
+    pub async fn own(source: &mut Source) -> (u8, u32, u8) {
+        let received = source.recv().await;
+        let locked = source.lock().await;
+        (received, locked, source.read().await)
+    }
+
+    pub async fn mixed(value: u8) -> u32 {
+        let counter = Arc::new(Mutex::new(0));
+        let mut source = Source { value };
+        let shared = Arc::clone(&counter);
+        *shared.lock().await += source.recv().await as u32;
+        let total = *counter.lock().await + source.lock().await;
+        total
+    }
+
+    pub async fn io_named(source: Source) -> (u8, (u8, u8), u8) {
+        let value = source.read_u8().await;
+        let halves = Source { value }.into_split();
+        (value, halves, source.shutdown().await)
+    }
+
+    pub async fn param(counter: &Mutex<u32>, source: Source) -> u32 {
+        *counter.lock().await + source.lock().await
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // sync
+    {
+        use self::blocking::*;
+
+        assert_eq!(own(&mut Source { value: 1 }), (2, 20, 2));
+        assert_eq!(mixed(1), 22);
+        assert_eq!(io_named(Source { value: 3 }), (3, (1, 1), 0));
+        let counter = std::sync::Mutex::new(1);
+        assert_eq!(param(&counter, Source { value: 1 }), 11);
+    }
+
+    // async
+    {
+        use self::asynchronous::*;
+
+        assert_eq!(own(&mut Source { value: 1 }).await, (2, 20, 2));
+        assert_eq!(mixed(1).await, 22);
+        assert_eq!(io_named(Source { value: 3 }).await, (3, (1, 1), 0));
+        let counter = tokio::sync::Mutex::new(1);
+        assert_eq!(param(&counter, Source { value: 1 }).await, 11);
+    }
+}
@@ -0,0 +1,25 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use futures::future::FutureExt;
+
+    pub async fn one() -> u32 {
+        1
+    }
+
+    pub async fn first() -> u32 {
+        futures::select! {
+            a = one().fuse() => a,
+            b = one().fuse() => b,
+        }
+    }
+
+    // the error alone is reported at the start of a statement
+    pub async fn first_plus_one() -> u32 {
+        futures::select!(a = one().fuse() => a, b = one().fuse() => b) + 1
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(join = "parallel")]
+mod amphi {
+    pub async fn my_fn() -> bool {
+        true
+    }
+}
+
+fn main() {}
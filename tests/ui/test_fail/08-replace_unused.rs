@@ -0,0 +1,13 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(replace(std::sync::Mutex => std::sync::RwLock, std::rc::Rc => std::sync::Arc))]
+mod amphi {
+    use std::rc::Rc;
+
+    pub async fn my_fn() -> Rc<bool> {
+        Rc::new(true)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(preset = "tokio")]
+mod amphi {
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    pub async fn exchange(stream: &mut TcpStream) -> std::io::Result<u32> {
+        let (mut reader, _writer) = tokio::io::split(stream);
+        let value = reader.read_u32().await?;
+        Ok(value)
+    }
+
+    pub async fn close(stream: &mut TcpStream) -> std::io::Result<()> {
+        stream.shutdown().await
+    }
+
+    pub async fn header<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<u16> {
+        reader.read_u16().await
+    }
+}
+
+fn main() {}
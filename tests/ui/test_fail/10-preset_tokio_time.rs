@@ -0,0 +1,31 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(preset = "tokio")]
+mod amphi {
+    use std::time::Duration;
+
+    use tokio::sync::broadcast;
+    use tokio::time::{interval, timeout};
+
+    pub async fn within(millis: u64) -> bool {
+        timeout(Duration::from_millis(millis), async {})
+            .await
+            .is_ok()
+    }
+
+    pub async fn tick(times: usize) {
+        let mut ticks = interval(Duration::from_millis(1));
+        for _ in 0..times {
+            ticks.tick().await;
+        }
+    }
+
+    pub async fn fan_out(value: u64) -> u64 {
+        let (tx, mut rx) = broadcast::channel(1);
+        tx.send(value).unwrap();
+        rx.recv().await.unwrap()
+    }
+}
+
+fn main() {}
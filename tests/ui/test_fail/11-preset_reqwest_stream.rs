@@ -0,0 +1,25 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(preset = "reqwest")]
+mod amphi {
+    use reqwest::{Body, Client};
+
+    pub async fn download(client: &Client, url: &str) -> reqwest::Result<usize> {
+        let response = client.get(url).send().await?;
+        let stream = response.bytes_stream();
+        Ok(std::mem::size_of_val(&stream))
+    }
+
+    pub async fn upload(client: &Client, url: &str, body: Vec<u8>) -> reqwest::Result<()> {
+        let stream = futures::stream::iter(vec![Ok::<_, std::io::Error>(body)]);
+        client
+            .post(url)
+            .body(Body::wrap_stream(stream))
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    #[amphi(any(asynchronous, local))]
+    pub fn local() {}
+
+    #[amphi(not(blocking, asynchronous))]
+    pub fn both() {}
+
+    #[amphi(blocking, derive(Clone))]
+    pub struct Derived;
+
+    pub struct Field {
+        #[amphi(async)]
+        pub field: u8,
+    }
+
+    pub fn tagged() {
+        #[amphi(sync)]
+        let _ = 1;
+    }
+}
+
+fn main() {}
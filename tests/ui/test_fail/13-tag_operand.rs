@@ -0,0 +1,11 @@
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    pub fn operand() -> u8 {
+        1 + #[amphi(asynchronous)]
+        2
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi]
+mod amphi {
+    use futures::stream::Stream;
+
+    pub fn ticks() -> impl Stream<Item = u32> {
+        async_stream::stream! {
+            let mut tick = 0;
+            loop {
+                yield tick;
+                tick += 1;
+            }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(preset = "tokio")]
+mod amphi {
+    pub async fn squares(n: u64) -> u64 {
+        let handles: Vec<_> = (0..n).map(|i| tokio::spawn(async move { i * i })).collect();
+        let mut sum = 0;
+        for handle in handles {
+            sum += handle.await.unwrap();
+        }
+        sum
+    }
+}
+
+fn main() {}
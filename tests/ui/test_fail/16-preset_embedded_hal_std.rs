@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+use amphi::amphi;
+
+#[amphi(preset = "embedded-hal", join = "thread")]
+mod amphi {
+    use futures::future::join_all;
+    use futures::join;
+    use futures::stream::{self, BoxStream, Stream, StreamExt};
+
+    pub async fn one() -> u32 {
+        1
+    }
+
+    pub async fn pair() -> (u32, u32) {
+        join!(one(), one())
+    }
+
+    pub async fn all() -> u32 {
+        join_all([one(), one()]).await.len() as u32
+    }
+
+    pub fn generated() -> impl Stream<Item = u32> {
+        async_stream::stream! {
+            yield 1;
+        }
+    }
+
+    pub fn boxed() -> BoxStream<'static, u32> {
+        stream::iter(0..2).boxed()
+    }
+}
+
+fn main() {}
@@ -19,11 +19,11 @@ async fn test_async_std() {
     let res = async_fn().await;
     #[amphi(blocking)]
     {
-        assert_eq!(res, false);
+        assert!(!res);
     }
     #[amphi(asynchronous)]
     {
-        assert_eq!(res, true);
+        assert!(res);
     }
 }
 
@@ -34,10 +34,10 @@ async fn test_tokio() {
     let res = async_fn().await;
     #[amphi(blocking)]
     {
-        assert_eq!(res, false);
+        assert!(!res);
     }
     #[amphi(asynchronous)]
     {
-        assert_eq!(res, true);
+        assert!(res);
     }
 }